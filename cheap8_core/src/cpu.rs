@@ -1,12 +1,28 @@
-use rand::prelude::*;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
 use std::fs::File;
+#[cfg(feature = "std")]
 use std::io::prelude::*;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
 use std::path::Path;
 
-use crate::display::{Display, HEIGHT, WIDTH};
+use crate::display::Display;
+use crate::error::{CpuError, LoadError, StateError};
+use crate::instruction::{self, DecodeError, Instruction};
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+use crate::state::{self, StateReader, StateWriter};
 use crate::timer::Timer;
 
-const START_PC: u16 = 0x200;
+/// Address where ROMs are loaded and executed from, unless
+/// changed with [`Cpu::set_load_address`].
+pub const DEFAULT_LOAD_ADDRESS: u16 = 0x200;
+pub(crate) const MEMORY_SIZE: usize = 0x10000;
+// XO-CHIP pitch until FX3A, the audio pattern is
+// then played at 4000 bits per second.
+const DEFAULT_PITCH: u8 = 64;
+const BIG_FONT_ADDRESS: usize = 0x50;
 const FONT: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
@@ -25,16 +41,51 @@ const FONT: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
     0xF0, 0x80, 0xF0, 0x80, 0x80,
 ]; // F
+   // SUPER-CHIP 8x10 font, the A to F digits come from Octo.
+const BIG_FONT: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x3C, 0x7E, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFE, 0xC3, 0xC3, 0xFE, 0xFE, 0xC3, 0xC3, 0xFE, 0xFC, // B
+    0x3C, 0x7E, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0x7E, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFE, 0xFE, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFE, 0xFE, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
 
 /// Output of CPU after update, it contains everything
 /// needed to output the game on a scree.
 pub struct Output<'a> {
-    /// The array of pixels, true is on, false is off.
-    pub screen: &'a [bool; WIDTH * HEIGHT],
+    /// The array of pixels, row by row. Each pixel is one of the
+    /// 4 colors, bit 0 is set iff it is on in the first plane and bit 1
+    /// iff it is on in the second one (XO-CHIP only).
+    pub screen: &'a [u8],
+    /// Width of the screen in the current resolution.
+    pub width: usize,
+    /// Height of the screen in the current resolution.
+    pub height: usize,
     /// True iff the screen should be updated.
     pub screen_update: bool,
     /// True iff a sound should be played.
     pub beep: bool,
+    /// The XO-CHIP 128 bits audio pattern to play while beeping,
+    /// `None` until the program loads one, in which case a plain
+    /// buzzer should be played.
+    pub audio_pattern: Option<&'a [u8; 16]>,
+    /// The XO-CHIP pitch, the pattern is played at
+    /// `4000 * 2^((pitch - 64) / 48)` bits per second.
+    pub pitch: u8,
+    /// True iff the program exited (with 00FD), the
+    /// CPU does nothing until it is reset.
+    pub exit: bool,
 }
 
 /// Cpu structs, that executes istructions.
@@ -49,16 +100,50 @@ pub struct Cpu {
     // derementing.
     delay_timer: Timer,
     sound_timer: Timer,
-    memory: [u8; 4096],
+    memory: [u8; MEMORY_SIZE],
     display: Display,
-    rng: ThreadRng,
+    rng: Rng,
+    // Seed of `rng` after each reset.
+    seed: u64,
     // True iff the screen should be updated.
     update_screen: bool,
+    quirks: Quirks,
+    // True iff the timers ticked since the last sprite was
+    // drawn, used by the display wait quirk.
+    vblank: bool,
+    // SUPER-CHIP RPL user flags, saved by FX75.
+    flags: [u8; 16],
+    // True iff the program exited.
+    exit: bool,
+    // Keys pressed during the current cycle.
+    keys: [bool; 16],
+    // Instructions executed since the last reset.
+    cycles: u64,
+    // Ticks of the timers since the last reset.
+    frames: u64,
+    // XO-CHIP audio pattern buffer, loaded by F002.
+    audio_pattern: Option<[u8; 16]>,
+    pitch: u8,
+    // Hash of the loaded ROM, identifying it in save states.
+    rom_hash: u64,
+    // Where the ROM is loaded, and the PC after a reset.
+    load_address: u16,
 }
 
 impl Cpu {
-    /// Create a new CPU with everything initialized to 0
-    pub fn new() -> Self {
+    /// Create a new CPU with everything initialized to 0,
+    /// that follows the given `quirks`. CXNN draws from a
+    /// generator seeded randomly.
+    #[cfg(feature = "rand")]
+    pub fn new(quirks: Quirks) -> Self {
+        Self::with_seed(quirks, rand::random())
+    }
+
+    #[cfg_attr(feature = "rand", doc = " Same as [`Cpu::new`], but")]
+    #[cfg_attr(not(feature = "rand"), doc = " Same as `Cpu::new`, but")]
+    /// CXNN draws from a generator seeded with `seed` after each reset,
+    /// so that runs of a ROM with the same inputs are reproducible.
+    pub fn with_seed(quirks: Quirks, seed: u64) -> Self {
         Cpu {
             register: [0; 16],
             index: 0,
@@ -67,234 +152,673 @@ impl Cpu {
             sp: 0,
             delay_timer: Timer::new(),
             sound_timer: Timer::new(),
-            memory: [0; 4096],
+            memory: [0; MEMORY_SIZE],
             display: Display::new(),
-            rng: rand::thread_rng(),
+            rng: Rng::new(seed),
+            seed,
             update_screen: false,
+            quirks,
+            vblank: true,
+            flags: [0; 16],
+            exit: false,
+            keys: [false; 16],
+            cycles: 0,
+            frames: 0,
+            audio_pattern: None,
+            pitch: DEFAULT_PITCH,
+            rom_hash: state::rom_hash(&[]),
+            load_address: DEFAULT_LOAD_ADDRESS,
         }
     }
 
     /// Reset to the values the CPU should have before loading a ROM.
-    pub fn reset(&mut self) -> () {
+    pub fn reset(&mut self) {
         self.register = [0; 16];
         self.index = 0;
-        self.pc = START_PC;
+        self.pc = self.load_address;
         self.stack = [0; 16];
         self.sp = 0;
         self.delay_timer.reset();
         self.sound_timer.reset();
-        self.memory = [0; 4096];
-        for i in 0..FONT.len() {
-            self.memory[i] = FONT[i];
+        self.memory = [0; MEMORY_SIZE];
+        self.memory[..FONT.len()].copy_from_slice(&FONT);
+        self.memory[BIG_FONT_ADDRESS..BIG_FONT_ADDRESS + BIG_FONT.len()].copy_from_slice(&BIG_FONT);
+        self.display.reset();
+        self.rng = Rng::new(self.seed);
+        self.vblank = true;
+        self.exit = false;
+        self.keys = [false; 16];
+        self.cycles = 0;
+        self.frames = 0;
+        self.audio_pattern = None;
+        self.pitch = DEFAULT_PITCH;
+    }
+
+    /// Execute one frame: `cycles_per_frame` instructions followed by
+    /// one tick of the timers, so calling it 60 times per second runs
+    /// the timers at the right speed. The frame ends early if the
+    /// program exits.
+    ///
+    /// Return an error if an instruction can't be executed, in which
+    /// case the CPU should not be cycled again before a [`Cpu::reset`].
+    pub fn run_frame(
+        &mut self,
+        key_pressed: &[bool; 16],
+        cycles_per_frame: usize,
+    ) -> Result<Output<'_>, CpuError> {
+        self.run_frame_with(key_pressed, cycles_per_frame, |_| ())
+    }
+
+    /// Same as [`Cpu::run_frame`], but `before_cycle` is called with
+    /// the CPU before each instruction is executed, for instance
+    #[cfg_attr(feature = "std", doc = " to [trace](crate::trace) them.")]
+    #[cfg_attr(not(feature = "std"), doc = " to trace them.")]
+    pub fn run_frame_with<F: FnMut(&Cpu)>(
+        &mut self,
+        key_pressed: &[bool; 16],
+        cycles_per_frame: usize,
+        mut before_cycle: F,
+    ) -> Result<Output<'_>, CpuError> {
+        let mut screen_update = false;
+        for _ in 0..cycles_per_frame {
+            if self.exit {
+                break;
+            }
+            before_cycle(self);
+            screen_update |= self.cycle(key_pressed)?.screen_update;
         }
-        self.display.clear();
-        self.rng = rand::thread_rng();
+        self.tick_timers();
+        self.update_screen = screen_update;
+
+        Ok(self.output())
     }
 
-    /// Execute one cycles (one instruction).
-    pub fn cycle(&mut self, key_pressed: &[bool; 16]) -> Output {
-        let opcode: u16 = (self.memory[self.pc as usize] as u16) << 8
-            | self.memory[(self.pc + 1) as usize] as u16;
-        self.pc += 2;
+    /// Decrement the delay and sound timers, for hosts driving their own
+    /// clock with [`Cpu::cycle`]. It should be called 60 times per second.
+    pub fn tick_timers(&mut self) {
         self.delay_timer.decrement();
-        let beep = self.sound_timer.decrement();
+        self.sound_timer.decrement();
+        self.vblank = true;
+        self.frames += 1;
+    }
+
+    /// Execute one cycles (one instruction), the timers
+    /// are left untouched.
+    ///
+    /// Return an error if the instruction can't be executed, in which
+    /// case the CPU should not be cycled again before a [`Cpu::reset`].
+    pub fn cycle(&mut self, key_pressed: &[bool; 16]) -> Result<Output<'_>, CpuError> {
         self.update_screen = false;
-        self.execute(opcode, key_pressed);
+        if self.exit {
+            return Ok(self.output());
+        }
+
+        let address = self.pc;
+        let range = self.memory_range(address, address as usize, 2)?;
+        let opcode = u16::from_be_bytes([self.memory[range.start], self.memory[range.start + 1]]);
+        let instruction = Self::decode(opcode).map_err(|e| CpuError::UnknownOpcode {
+            address,
+            opcode: e.opcode,
+        })?;
+        self.keys = *key_pressed;
+        self.cycles += 1;
+        self.execute(instruction)?;
+
+        Ok(self.output())
+    }
 
+    /// Current output of the CPU, `screen_update` telling
+    /// whether the last cycle or frame changed the screen.
+    pub fn output(&self) -> Output<'_> {
         Output {
             screen: self.display.get(),
+            width: self.display.width(),
+            height: self.display.height(),
             screen_update: self.update_screen,
-            beep,
+            beep: self.sound_timer.timer > 0,
+            audio_pattern: self.audio_pattern.as_ref(),
+            pitch: self.pitch,
+            exit: self.exit,
         }
     }
 
-    fn execute(&mut self, opcode: u16, key_pressed: &[bool; 16]) -> () {
-        let x = ((opcode & 0x0F00) >> 8) as usize;
-        let y = ((opcode & 0x00F0) >> 4) as usize;
-        let nn = (opcode & 0x00FF) as u8;
-        let nnn = opcode & 0x0FFF;
-        let n = opcode & 0x000F;
-        let vx = self.register[x];
-        let vy = self.register[y];
-
-        let op_1 = (opcode & 0xF000) >> 12;
-        let op_2 = (opcode & 0x0F00) >> 8;
-        let op_3 = (opcode & 0x00F0) >> 4;
-        let op_4 = opcode & 0x000F;
-
-        match op_1 {
-            0x0 => match (op_3, op_4) {
-                (0xE, 0x0) => {
-                    self.display.clear();
-                    self.update_screen = true;
-                } //clear screen
-                (0xE, 0xE) => self.pc = self.pop(), //return
-                _ => panic!(
-                    "Unsupported instruction {:#x}{:#x}{:#x}{:#x}",
-                    op_1, op_2, op_3, op_4
-                ),
-            },
-            0x1 => self.pc = nnn, //jump
-            0x2 => {
-                self.push(self.pc);
+    /// Decode the `opcode` word into an [`Instruction`],
+    /// see [`decode`](crate::decode).
+    pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+        instruction::decode(opcode)
+    }
+
+    /// Execute `instruction` as if it was the one at the PC, which
+    /// is moved past it. Key instructions see the keys given to the
+    /// last [`Cpu::cycle`].
+    ///
+    /// Return an error if the instruction can't be executed, in which
+    /// case the CPU should not be cycled again before a [`Cpu::reset`].
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), CpuError> {
+        use Instruction::*;
+
+        let address = self.pc;
+        self.pc = self.pc.wrapping_add(2);
+
+        match instruction {
+            Clear => {
+                self.display.clear();
+                self.update_screen = true;
+            }
+            Return => self.pc = self.pop(address)?,
+            ScrollDown { n } => {
+                self.display.scroll_down(n as usize);
+                self.update_screen = true;
+            }
+            ScrollUp { n } => {
+                self.display.scroll_up(n as usize);
+                self.update_screen = true;
+            }
+            ScrollRight => {
+                self.display.scroll_right(4);
+                self.update_screen = true;
+            }
+            ScrollLeft => {
+                self.display.scroll_left(4);
+                self.update_screen = true;
+            }
+            Exit => self.exit = true,
+            LowRes | HighRes => {
+                self.display.set_hires(instruction == HighRes);
+                self.update_screen = true;
+            }
+            Jump { nnn } => self.pc = nnn,
+            Call { nnn } => {
+                self.push(address, self.pc)?;
                 self.pc = nnn;
             }
-            0x3 => {
-                if vx == nn {
-                    self.pc += 2;
+            SkipEqImm { x, nn } => {
+                if self.register[x as usize] == nn {
+                    self.skip();
                 }
             }
-            0x4 => {
-                if vx != nn {
-                    self.pc += 2;
+            SkipNeImm { x, nn } => {
+                if self.register[x as usize] != nn {
+                    self.skip();
                 }
             }
-            0x5 => {
-                if vx == vy {
-                    self.pc += 2;
+            SkipEq { x, y } => {
+                if self.register[x as usize] == self.register[y as usize] {
+                    self.skip();
                 }
             }
-            0x9 => {
-                if vx != vy {
-                    self.pc += 2;
+            SkipNe { x, y } => {
+                if self.register[x as usize] != self.register[y as usize] {
+                    self.skip();
                 }
             }
-            0x6 => self.register[x] = nn, //set register
-            0x7 => self.register[x] = vx.wrapping_add(nn), //add to register
-            0x8 => match op_4 {
-                0x0 => self.register[x] = vy,
-                0x1 => self.register[x] = vx | vy,
-                0x2 => self.register[x] = vx & vy,
-                0x3 => self.register[x] = vx ^ vy,
-                0x4 => {
-                    let (res, ovf) = vx.overflowing_add(vy);
-                    self.register[x] = res;
-                    self.register[0xF] = ovf as u8;
+            SaveRange { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let range = self.memory_range(address, self.index as usize, x.abs_diff(y) + 1)?;
+                for (offset, i) in range.enumerate() {
+                    self.memory[i] = self.register[Self::register_at(x, y, offset)];
                 }
-                0x5 => {
-                    self.register[x] = vx.wrapping_sub(vy);
-                    self.register[0xF] = (vx >= vy) as u8;
-                }
-                0x6 => {
-                    self.register[0xF] = vx & 0x1;
-                    self.register[x] >>= 1;
+            }
+            LoadRange { x, y } => {
+                let (x, y) = (x as usize, y as usize);
+                let range = self.memory_range(address, self.index as usize, x.abs_diff(y) + 1)?;
+                for (offset, i) in range.enumerate() {
+                    self.register[Self::register_at(x, y, offset)] = self.memory[i];
                 }
-                0x7 => {
-                    self.register[x] = vy.wrapping_sub(vx);
-                    self.register[0xF] = (vy >= vx) as u8;
+            }
+            SetImm { x, nn } => self.register[x as usize] = nn,
+            AddImm { x, nn } => {
+                self.register[x as usize] = self.register[x as usize].wrapping_add(nn)
+            }
+            Set { x, y } => self.register[x as usize] = self.register[y as usize],
+            Or { x, y } | And { x, y } | Xor { x, y } => {
+                let (vx, vy) = (self.register[x as usize], self.register[y as usize]);
+                self.register[x as usize] = match instruction {
+                    Or { .. } => vx | vy,
+                    And { .. } => vx & vy,
+                    _ => vx ^ vy,
+                };
+                if self.quirks.vf_reset {
+                    self.register[0xF] = 0;
                 }
-                0xE => {
-                    self.register[0xF] = vx & 0x80;
-                    self.register[x] <<= 1;
+            }
+            Add { x, y } => {
+                let (res, ovf) =
+                    self.register[x as usize].overflowing_add(self.register[y as usize]);
+                self.register[x as usize] = res;
+                self.register[0xF] = ovf as u8;
+            }
+            Sub { x, y } => {
+                let (vx, vy) = (self.register[x as usize], self.register[y as usize]);
+                self.register[x as usize] = vx.wrapping_sub(vy);
+                self.register[0xF] = (vx >= vy) as u8;
+            }
+            SubReverse { x, y } => {
+                let (vx, vy) = (self.register[x as usize], self.register[y as usize]);
+                self.register[x as usize] = vy.wrapping_sub(vx);
+                self.register[0xF] = (vy >= vx) as u8;
+            }
+            ShiftRight { x, y } => {
+                let source = self.shift_source(x, y);
+                self.register[x as usize] = source >> 1;
+                self.register[0xF] = source & 0x1;
+            }
+            ShiftLeft { x, y } => {
+                let source = self.shift_source(x, y);
+                self.register[x as usize] = source << 1;
+                self.register[0xF] = source >> 7;
+            }
+            SetIndex { nnn } => self.index = nnn,
+            JumpOffset { x, nnn } => {
+                let offset = if self.quirks.jump_vx {
+                    self.register[x as usize]
+                } else {
+                    self.register[0]
+                };
+                self.pc = nnn + offset as u16;
+            }
+            Random { x, nn } => {
+                let rnd = self.rng.next_u8();
+                self.register[x as usize] = rnd & nn;
+            }
+            Draw { x, y, n } => {
+                if self.quirks.display_wait && !self.vblank {
+                    self.pc = address;
+                    return Ok(());
                 }
-                _ => panic!(
-                    "Unsupported instruction {:#x}{:#x}{:#x}{:#x}",
-                    op_1, op_2, op_3, op_4
-                ),
-            },
-            0xA => self.index = nnn,
-            0xB => self.pc = nnn + self.register[0] as u16,
-            0xC => {
-                let rnd: u8 = self.rng.gen();
-                self.register[x] = rnd & nn;
-            }
-            0xE => match (op_3, op_4) {
-                (0x9, 0xE) => {
-                    if key_pressed[vx as usize] {
-                        self.pc += 2;
-                    }
+                self.vblank = false;
+                let (vx, vy) = (self.register[x as usize], self.register[y as usize]);
+                let clip = self.quirks.clip_sprites;
+                let planes = self.display.plane_count();
+                let vf = if n == 0 {
+                    let range = self.memory_range(address, self.index as usize, 32 * planes)?;
+                    self.display
+                        .draw_large(vx as usize, vy as usize, &self.memory[range], clip)
+                } else {
+                    let range =
+                        self.memory_range(address, self.index as usize, n as usize * planes)?;
+                    self.display
+                        .draw(vx as usize, vy as usize, &self.memory[range], clip)
+                };
+                self.register[0xF] = vf as u8;
+                self.update_screen = true;
+            }
+            SkipKey { x } => {
+                if self.keys[(self.register[x as usize] & 0xF) as usize] {
+                    self.skip();
                 }
-                (0xA, 0x1) => {
-                    if !key_pressed[vx as usize] {
-                        self.pc += 2;
-                    }
+            }
+            SkipNotKey { x } => {
+                if !self.keys[(self.register[x as usize] & 0xF) as usize] {
+                    self.skip();
                 }
-                _ => panic!(
-                    "Unsupported instruction {:#x}{:#x}{:#x}{:#x}",
-                    op_1, op_2, op_3, op_4
-                ),
+            }
+            SetIndexLong => {
+                let range = self.memory_range(address, self.pc as usize, 2)?;
+                self.index =
+                    u16::from_be_bytes([self.memory[range.start], self.memory[range.start + 1]]);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            SelectPlanes { x } => self.display.select_planes(x),
+            LoadAudio => {
+                let range = self.memory_range(address, self.index as usize, 16)?;
+                let mut pattern = [0; 16];
+                pattern.copy_from_slice(&self.memory[range]);
+                self.audio_pattern = Some(pattern);
+            }
+            GetDelay { x } => self.register[x as usize] = self.delay_timer.timer,
+            WaitKey { x } => match self.keys.iter().position(|key| *key) {
+                Some(key) => self.register[x as usize] = key as u8,
+                None => self.pc = address,
             },
-            0xF => match (op_3, op_4) {
-                (0x0, 0xA) => {
-                    for (i, key) in key_pressed.into_iter().enumerate() {
-                        if *key {
-                            self.register[x] = i as u8;
-                            return;
-                        }
-                    }
-                    self.pc -= 2;
-                }
-                (0x0, 0x7) => self.register[x] = self.delay_timer.timer,
-                (0x1, 0x5) => self.delay_timer.timer = vx,
-                (0x1, 0x8) => self.sound_timer.timer = vx,
-                (0x1, 0xE) => self.index += vx as u16,
-                (0x2, 0x9) => self.index = (vx & 0xF) as u16 * 5,
-                (0x3, 0x3) => {
-                    let digit1 = vx / 100;
-                    let digit2 = (vx % 100) / 10;
-                    let digit3 = vx % 10;
-                    self.memory[self.index as usize] = digit1;
-                    self.memory[(self.index + 1) as usize] = digit2;
-                    self.memory[(self.index + 2) as usize] = digit3;
-                }
-                (0x5, 0x5) => {
-                    for i in 0..=x {
-                        self.memory[self.index as usize + i] = self.register[i];
-                    }
+            SetDelay { x } => self.delay_timer.timer = self.register[x as usize],
+            SetSound { x } => self.sound_timer.timer = self.register[x as usize],
+            AddIndex { x } => {
+                self.index = self.index.wrapping_add(self.register[x as usize] as u16)
+            }
+            Font { x } => self.index = (self.register[x as usize] & 0xF) as u16 * 5,
+            BigFont { x } => {
+                let digit = (self.register[x as usize] & 0xF) as usize;
+                self.index = (BIG_FONT_ADDRESS + digit * 10) as u16;
+            }
+            Bcd { x } => {
+                let vx = self.register[x as usize];
+                let range = self.memory_range(address, self.index as usize, 3)?;
+                self.memory[range].copy_from_slice(&[vx / 100, (vx % 100) / 10, vx % 10]);
+            }
+            Pitch { x } => self.pitch = self.register[x as usize],
+            Store { x } => {
+                let x = x as usize;
+                let range = self.memory_range(address, self.index as usize, x + 1)?;
+                self.memory[range].copy_from_slice(&self.register[..=x]);
+                if self.quirks.load_store_increment {
+                    self.index = self.index.wrapping_add(x as u16 + 1);
                 }
-                (0x6, 0x5) => {
-                    for i in 0..=x {
-                        self.register[i] = self.memory[self.index as usize + i];
-                    }
+            }
+            Load { x } => {
+                let x = x as usize;
+                let range = self.memory_range(address, self.index as usize, x + 1)?;
+                self.register[..=x].copy_from_slice(&self.memory[range]);
+                if self.quirks.load_store_increment {
+                    self.index = self.index.wrapping_add(x as u16 + 1);
                 }
-                _ => panic!(
-                    "Unsupported instruction {:#x}{:#x}{:#x}{:#x}",
-                    op_1, op_2, op_3, op_4
-                ),
-            },
-            0xD => {
-                let vf = self.display.draw(
-                    vx as usize,
-                    vy as usize,
-                    &self.memory[self.index as usize..(self.index + n) as usize],
-                );
-                self.register[0xF] = vf as u8;
-                self.update_screen = true;
             }
-            _ => panic!(
-                "Unsupported instruction {:#x}{:#x}{:#x}{:#x}",
-                op_1, op_2, op_3, op_4
-            ),
+            SaveFlags { x } => {
+                let x = x as usize;
+                self.flags[..=x].copy_from_slice(&self.register[..=x]);
+            }
+            LoadFlags { x } => {
+                let x = x as usize;
+                self.register[..=x].copy_from_slice(&self.flags[..=x]);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Operand of the 8XY6 and 8XYE shifts, VY with the shift quirk.
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        if self.quirks.shift_vy {
+            self.register[y as usize]
+        } else {
+            self.register[x as usize]
+        }
+    }
+
+    // Skip the next instruction, which is 4 bytes long
+    // if it is a F000 NNNN long index load.
+    fn skip(&mut self) {
+        let pc = self.pc as usize;
+        let long = pc + 1 < MEMORY_SIZE && self.memory[pc] == 0xF0 && self.memory[pc + 1] == 0x00;
+        let len = if long { 4 } else { 2 };
+        self.pc = self.pc.wrapping_add(len);
+    }
+
+    // Register at `offset` in the range from VX to VY,
+    // which is in descending order if X > Y.
+    fn register_at(x: usize, y: usize, offset: usize) -> usize {
+        if x <= y {
+            x + offset
+        } else {
+            x - offset
         }
     }
 
     // Stack operations
-    fn push(&mut self, value: u16) -> () {
-        self.stack[self.sp as usize] = value;
+    fn push(&mut self, address: u16, value: u16) -> Result<(), CpuError> {
+        let slot = self
+            .stack
+            .get_mut(self.sp as usize)
+            .ok_or(CpuError::StackOverflow { address })?;
+        *slot = value;
         self.sp += 1;
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
+    fn pop(&mut self, address: u16) -> Result<u16, CpuError> {
+        if self.sp == 0 {
+            return Err(CpuError::StackUnderflow { address });
+        }
         self.sp -= 1;
-        self.stack[self.sp as usize]
+        Ok(self.stack[self.sp as usize])
     }
 
-    /// Load the ROM located at path
-    pub fn load(&mut self, path: &str) {
-        let path = Path::new(path);
+    // Range of `len` bytes of memory starting at `start`, accessed
+    // by the instruction at `address`.
+    fn memory_range(
+        &self,
+        address: u16,
+        start: usize,
+        len: usize,
+    ) -> Result<Range<usize>, CpuError> {
+        if start + len > MEMORY_SIZE {
+            return Err(CpuError::MemoryOutOfRange {
+                address,
+                access: start.max(MEMORY_SIZE),
+            });
+        }
+        Ok(start..start + len)
+    }
 
-        let mut file = match File::open(path) {
-            Err(e) => panic!("couldn't open {}: {}", path.display(), e),
-            Ok(file) => file,
-        };
+    /// Change where the ROMs are loaded, which is also where they start
+    /// executing from after the next [`Cpu::reset`]. For instance
+    /// ETI-660 programs start at 0x600.
+    pub fn set_load_address(&mut self, address: u16) {
+        self.load_address = address;
+    }
 
+    /// Address where the ROMs are loaded.
+    pub fn load_address(&self) -> u16 {
+        self.load_address
+    }
+
+    /// Load the ROM located at path, there is no
+    /// file system to load it from in browsers.
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), LoadError> {
+        self.load_reader(File::open(path)?)
+    }
+
+    /// Load the ROM read from `reader`.
+    #[cfg(feature = "std")]
+    pub fn load_reader<R: Read>(&mut self, mut reader: R) -> Result<(), LoadError> {
         let mut buffer = Vec::new();
-        if let Err(e) = file.read_to_end(&mut buffer) {
-            panic!("couldn't read the file: {}", e);
+        reader.read_to_end(&mut buffer)?;
+        self.load_bytes(&buffer)
+    }
+
+    /// Load `rom` in memory at the load address, fail
+    /// without touching memory if it doesn't fit.
+    pub fn load_bytes(&mut self, rom: &[u8]) -> Result<(), LoadError> {
+        let start = self.load_address as usize;
+        let max = MEMORY_SIZE - start;
+        if rom.len() > max {
+            return Err(LoadError::RomTooLarge {
+                size: rom.len(),
+                max,
+            });
         }
 
-        for i in 0x200..self.memory.len().min(buffer.len() + 0x200) {
-            self.memory[i] = buffer.get(i - 0x200).unwrap().clone();
+        self.memory[start..start + rom.len()].copy_from_slice(rom);
+        self.rom_hash = state::rom_hash(rom);
+        Ok(())
+    }
+
+    /// Seed of the random generator used by CXNN.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Hash of the loaded ROM.
+    pub fn rom_hash(&self) -> u64 {
+        self.rom_hash
+    }
+
+    /// Number of instructions executed since the last reset, counting
+    /// those waiting for a key or the display once per cycle.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Number of frames since the last reset, counted
+    /// as the ticks of the timers.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Registers V0 to VF.
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.register
+    }
+
+    /// Index register I.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// Address of the next instruction.
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Return addresses of the subroutines being executed,
+    /// the innermost one last.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer.timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer.timer
+    }
+
+    /// The whole 64 KiB of memory.
+    pub fn memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// True iff the program exited (with 00FD).
+    pub fn has_exited(&self) -> bool {
+        self.exit
+    }
+
+    /// Snapshot of the whole machine, that can be restored
+    /// with [`Cpu::load_state`] while the same ROM is loaded.
+    pub fn save_state(&self) -> Vec<u8> {
+        self.write_state(StateWriter::new())
+    }
+
+    /// Restore a snapshot taken by [`Cpu::save_state`]. On error,
+    /// the CPU is left untouched.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        self.read_state(StateReader::new(bytes))
+    }
+
+    /// Same as [`Cpu::save_state`], but without compression so that
+    /// the snapshots all have the same size, and can be diffed.
+    pub(crate) fn snapshot(&self) -> Vec<u8> {
+        self.write_state(StateWriter::uncompressed())
+    }
+
+    /// Restore a snapshot taken by [`Cpu::snapshot`].
+    pub(crate) fn restore(&mut self, bytes: &[u8]) -> Result<(), StateError> {
+        self.read_state(StateReader::uncompressed(bytes))
+    }
+
+    fn write_state(&self, mut state: StateWriter) -> Vec<u8> {
+        state.bytes(state::MAGIC);
+        state.u8(state::VERSION);
+        state.u64(self.rom_hash);
+
+        state.bytes(&self.register);
+        state.u16(self.index);
+        state.u16(self.pc);
+        self.stack.iter().for_each(|address| state.u16(*address));
+        state.u8(self.sp);
+        state.u8(self.delay_timer.timer);
+        state.u8(self.sound_timer.timer);
+        state.packed(&self.memory);
+        self.display.save(&mut state);
+        state.bool(self.update_screen);
+        state.u64(self.rng.state());
+        state.bool(self.vblank);
+        state.bytes(&self.flags);
+        state.bool(self.exit);
+        state.bool(self.audio_pattern.is_some());
+        state.bytes(&self.audio_pattern.unwrap_or_default());
+        state.u8(self.pitch);
+        state.u64(self.cycles);
+        state.u64(self.frames);
+        state.into_bytes()
+    }
+
+    fn read_state(&mut self, mut state: StateReader) -> Result<(), StateError> {
+        if state.bytes(state::MAGIC.len()) != Ok(state::MAGIC) {
+            return Err(StateError::NotAState);
+        }
+        let version = state.u8()?;
+        if version != state::VERSION {
+            return Err(StateError::UnsupportedVersion { version });
         }
+        let found = state.u64()?;
+        if found != self.rom_hash {
+            return Err(StateError::RomMismatch {
+                expected: self.rom_hash,
+                found,
+            });
+        }
+
+        // Read the whole state before changing the CPU, so that it is left
+        // untouched on error. The memory, too large to be copied around,
+        // is only checked until then
+        let mut register = [0; 16];
+        register.copy_from_slice(state.bytes(16)?);
+        let index = state.u16()?;
+        let pc = state.u16()?;
+        let mut stack = [0; 16];
+        for address in stack.iter_mut() {
+            *address = state.u16()?;
+        }
+        let sp = state.u8()?;
+        if sp as usize > stack.len() {
+            return Err(StateError::Corrupted);
+        }
+        let delay_timer = state.u8()?;
+        let sound_timer = state.u8()?;
+        let memory = state.packed_slice(MEMORY_SIZE)?;
+        let mut display = Display::new();
+        display.load(&mut state)?;
+        let update_screen = state.bool()?;
+        let rng = state.u64()?;
+        let vblank = state.bool()?;
+        let mut flags = [0; 16];
+        flags.copy_from_slice(state.bytes(16)?);
+        let exit = state.bool()?;
+        let has_pattern = state.bool()?;
+        let mut pattern = [0; 16];
+        pattern.copy_from_slice(state.bytes(16)?);
+        let pitch = state.u8()?;
+        let cycles = state.u64()?;
+        let frames = state.u64()?;
+        state.finish()?;
+
+        self.register = register;
+        self.index = index;
+        self.pc = pc;
+        self.stack = stack;
+        self.sp = sp;
+        self.delay_timer.timer = delay_timer;
+        self.sound_timer.timer = sound_timer;
+        memory.unpack(&mut self.memory);
+        self.display = display;
+        self.update_screen = update_screen;
+        self.rng = Rng::new(rng);
+        self.vblank = vblank;
+        self.flags = flags;
+        self.exit = exit;
+        self.keys = [false; 16];
+        self.audio_pattern = has_pattern.then_some(pattern);
+        self.pitch = pitch;
+        self.cycles = cycles;
+        self.frames = frames;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rand")]
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new(Quirks::default())
     }
 }
@@ -0,0 +1,332 @@
+//! Debugger, to run a [`Cpu`] until something interesting happens.
+//!
+//! The [`Debugger`] executes the instructions one by one, stopping on
+//! breakpoints, on watched memory accesses and when the current
+//! goal (a number of steps, leaving a subroutine...) is reached.
+//! It ticks the timers itself, after every `cycles_per_frame`
+//! instructions, so it replaces [`Cpu::run_frame`].
+use alloc::vec::Vec;
+use core::ops::{Range, RangeInclusive};
+
+use crate::cpu::Cpu;
+use crate::error::CpuError;
+use crate::instruction::{decode, Instruction};
+
+/// Stops the execution before the instruction at `address`,
+/// if the `condition` holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub condition: Option<Condition>,
+}
+
+/// Comparison of a register to a value, like `v3 == 0x10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: u8,
+    pub comparison: Comparison,
+    pub value: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+}
+
+impl Condition {
+    /// True iff the condition holds for the registers of `cpu`.
+    pub fn holds(&self, cpu: &Cpu) -> bool {
+        let register = cpu.registers()[(self.register & 0xF) as usize];
+        match self.comparison {
+            Comparison::Equal => register == self.value,
+            Comparison::NotEqual => register != self.value,
+            Comparison::Less => register < self.value,
+            Comparison::Greater => register > self.value,
+        }
+    }
+}
+
+/// Stops the execution after an instruction reads or
+/// writes memory in `range`, depending on `read` and `write`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: RangeInclusive<u16>,
+    pub read: bool,
+    pub write: bool,
+}
+
+/// Kind of memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Why [`Debugger::run`] returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The goal set by the last stepping method was reached.
+    Done,
+    /// The PC reached a breakpoint.
+    Breakpoint { address: u16 },
+    /// The instruction at `address` accessed the watched memory at `location`.
+    Watchpoint {
+        address: u16,
+        access: Access,
+        location: u16,
+    },
+    /// The program exited.
+    Exit,
+    /// The instruction budget was spent before anything else happened.
+    Budget,
+}
+
+// Where the execution should stop, besides breakpoints and watchpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    // Run until stopped.
+    Continue,
+    // Execute this many more instructions.
+    Steps(usize),
+    // Run until the PC is `address` with `depth` subroutines on the stack.
+    Return { address: u16, depth: usize },
+    // Run until less than this many subroutines are on the stack.
+    Depth(usize),
+    Address(u16),
+}
+
+/// Debugger holding the breakpoints and watchpoints, see the
+/// [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    goal: Goal,
+    cycles_per_frame: usize,
+    // Instructions executed since the timers last ticked.
+    cycles: usize,
+    // Address the last run stopped at, so that resuming
+    // doesn't stop again on the same breakpoint.
+    stopped_at: Option<u16>,
+}
+
+impl Debugger {
+    /// Create a debugger without breakpoints, that ticks the timers
+    /// every `cycles_per_frame` instructions and runs until stopped.
+    pub fn new(cycles_per_frame: usize) -> Self {
+        Debugger {
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            goal: Goal::Continue,
+            cycles_per_frame: cycles_per_frame.max(1),
+            cycles: 0,
+            stopped_at: None,
+        }
+    }
+
+    pub fn set_cycles_per_frame(&mut self, cycles_per_frame: usize) {
+        self.cycles_per_frame = cycles_per_frame.max(1);
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    /// Remove and return the breakpoint at `index` in [`Debugger::breakpoints`].
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        (index < self.breakpoints.len()).then(|| self.breakpoints.remove(index))
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Remove and return the watchpoint at `index` in [`Debugger::watchpoints`].
+    pub fn remove_watchpoint(&mut self, index: usize) -> Option<Watchpoint> {
+        (index < self.watchpoints.len()).then(|| self.watchpoints.remove(index))
+    }
+
+    /// Make the next run stop after `count` instructions.
+    pub fn step(&mut self, count: usize) {
+        self.goal = Goal::Steps(count);
+    }
+
+    /// Make the next run stop after the current instruction, running
+    /// the whole subroutine if it is a call (2NNN).
+    pub fn step_over(&mut self, cpu: &Cpu) {
+        self.goal = match current_instruction(cpu) {
+            Some(Instruction::Call { .. }) => Goal::Return {
+                address: cpu.pc().wrapping_add(2),
+                depth: cpu.stack().len(),
+            },
+            _ => Goal::Steps(1),
+        };
+    }
+
+    /// Make the next run stop once the current subroutine returned
+    /// (with 00EE). Outside of subroutines, it is the same as a step.
+    pub fn step_out(&mut self, cpu: &Cpu) {
+        self.goal = match cpu.stack().len() {
+            0 => Goal::Steps(1),
+            depth => Goal::Depth(depth),
+        };
+    }
+
+    /// Make the next run stop when the PC reaches `address`.
+    pub fn run_to(&mut self, address: u16) {
+        self.goal = Goal::Address(address);
+    }
+
+    /// Make the next run stop only on breakpoints and watchpoints.
+    pub fn resume(&mut self) {
+        self.goal = Goal::Continue;
+    }
+
+    /// Forget the goal and the state of the last run when the program
+    /// restarts, keeping the breakpoints and watchpoints.
+    pub fn restart(&mut self) {
+        self.goal = Goal::Continue;
+        self.cycles = 0;
+        self.stopped_at = None;
+    }
+
+    /// Execute at most `budget` instructions with the keys in `key_pressed`,
+    /// until a breakpoint, a watchpoint or the goal is reached. Calling it
+    /// with `cycles_per_frame` as the budget 60 times per second runs
+    /// the program at the normal speed.
+    ///
+    /// Return an error if an instruction can't be executed, see [`Cpu::cycle`].
+    pub fn run(
+        &mut self,
+        cpu: &mut Cpu,
+        key_pressed: &[bool; 16],
+        budget: usize,
+    ) -> Result<Stop, CpuError> {
+        for _ in 0..budget {
+            if let Some(stop) = self.check(cpu) {
+                return Ok(self.stop(cpu, stop));
+            }
+
+            let address = cpu.pc();
+            let access = current_instruction(cpu).and_then(|i| memory_access(cpu, &i));
+            cpu.cycle(key_pressed)?;
+            self.stopped_at = None;
+            if let Goal::Steps(steps) = &mut self.goal {
+                *steps -= 1;
+            }
+            self.cycles += 1;
+            if self.cycles >= self.cycles_per_frame {
+                cpu.tick_timers();
+                self.cycles = 0;
+            }
+
+            // Instructions that wait (for a key or the display)
+            // stay on the same address without accessing memory
+            if cpu.pc() == address {
+                continue;
+            }
+            if let Some((kind, range)) = access {
+                if let Some(location) = self.watched(kind, range) {
+                    let stop = Stop::Watchpoint {
+                        address,
+                        access: kind,
+                        location,
+                    };
+                    return Ok(self.stop(cpu, stop));
+                }
+            }
+        }
+
+        Ok(match self.check(cpu) {
+            Some(stop) => self.stop(cpu, stop),
+            None => Stop::Budget,
+        })
+    }
+
+    // Forget the goal once the run stopped.
+    fn stop(&mut self, cpu: &Cpu, stop: Stop) -> Stop {
+        self.goal = Goal::Continue;
+        self.stopped_at = Some(cpu.pc());
+        stop
+    }
+
+    // Reason to stop before executing the instruction at the PC.
+    fn check(&self, cpu: &Cpu) -> Option<Stop> {
+        let pc = cpu.pc();
+        if cpu.has_exited() {
+            return Some(Stop::Exit);
+        }
+        let done = match self.goal {
+            Goal::Continue => false,
+            Goal::Steps(steps) => steps == 0,
+            Goal::Return { address, depth } => pc == address && cpu.stack().len() == depth,
+            Goal::Depth(depth) => cpu.stack().len() < depth,
+            Goal::Address(address) => pc == address,
+        };
+        if done {
+            return Some(Stop::Done);
+        }
+        let breakpoint = self.stopped_at != Some(pc)
+            && self.breakpoints.iter().any(|breakpoint| {
+                breakpoint.address == pc && breakpoint.condition.is_none_or(|c| c.holds(cpu))
+            });
+        breakpoint.then_some(Stop::Breakpoint { address: pc })
+    }
+
+    // First watched address accessed in `range`.
+    fn watched(&self, access: Access, range: Range<usize>) -> Option<u16> {
+        self.watchpoints
+            .iter()
+            .filter(|watchpoint| match access {
+                Access::Read => watchpoint.read,
+                Access::Write => watchpoint.write,
+            })
+            .filter_map(|watchpoint| {
+                let start = range.start.max(*watchpoint.range.start() as usize);
+                let end = range.end.min(*watchpoint.range.end() as usize + 1);
+                (start < end).then_some(start as u16)
+            })
+            .min()
+    }
+}
+
+/// The instruction at the PC of `cpu`, if it is one.
+pub fn current_instruction(cpu: &Cpu) -> Option<Instruction> {
+    let pc = cpu.pc() as usize;
+    let bytes = cpu.memory().get(pc..pc + 2)?;
+    decode(u16::from_be_bytes([bytes[0], bytes[1]])).ok()
+}
+
+// Memory accessed by `instruction` if it was executed by `cpu` now,
+// the instruction fetch aside.
+fn memory_access(cpu: &Cpu, instruction: &Instruction) -> Option<(Access, Range<usize>)> {
+    use Instruction::*;
+
+    let index = cpu.index() as usize;
+    let (access, start, len) = match *instruction {
+        SaveRange { x, y } => (Access::Write, index, x.abs_diff(y) as usize + 1),
+        Bcd { .. } => (Access::Write, index, 3),
+        Store { x } => (Access::Write, index, x as usize + 1),
+        LoadRange { x, y } => (Access::Read, index, x.abs_diff(y) as usize + 1),
+        Load { x } => (Access::Read, index, x as usize + 1),
+        LoadAudio => (Access::Read, index, 16),
+        Draw { n, .. } => {
+            let rows = if n == 0 { 32 } else { n as usize };
+            (Access::Read, index, rows * cpu.display().plane_count())
+        }
+        SetIndexLong => (Access::Read, cpu.pc() as usize + 2, 2),
+        _ => return None,
+    };
+    Some((access, start..start + len))
+}
@@ -0,0 +1,285 @@
+//! Disassembler, to inspect what a ROM does.
+//!
+//! Single instructions are formatted with [`format()`], and
+//! whole ROMs are listed with [`listing`], which separates
+//! the code from the data by following the control flow.
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use crate::cpu::MEMORY_SIZE;
+use crate::instruction::{decode, Instruction};
+
+/// Syntax of the disassembled instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    /// Octo assembly language, like `v0 += 0x12`.
+    Octo,
+    /// Classic mnemonics, like `ADD V0, #12`.
+    Classic,
+}
+
+/// One line of a ROM listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    /// An instruction reachable from the entry point. `long` is
+    /// the address following a [`Instruction::SetIndexLong`].
+    Code {
+        address: u16,
+        opcode: u16,
+        instruction: Instruction,
+        long: Option<u16>,
+    },
+    /// A byte that is never executed.
+    Data { address: u16, byte: u8 },
+}
+
+/// Format `instruction` in the given `syntax`. The address following
+/// [`Instruction::SetIndexLong`] is not part of the instruction, see
+/// [`Line`] to get it.
+pub fn format(instruction: &Instruction, syntax: Syntax) -> String {
+    match syntax {
+        Syntax::Octo => octo(instruction),
+        Syntax::Classic => classic(instruction),
+    }
+}
+
+fn octo(instruction: &Instruction) -> String {
+    use Instruction::*;
+
+    match *instruction {
+        ScrollDown { n } => format!("scroll-down {}", n),
+        ScrollUp { n } => format!("scroll-up {}", n),
+        Clear => "clear".to_string(),
+        Return => "return".to_string(),
+        ScrollRight => "scroll-right".to_string(),
+        ScrollLeft => "scroll-left".to_string(),
+        Exit => "exit".to_string(),
+        LowRes => "lores".to_string(),
+        HighRes => "hires".to_string(),
+        Jump { nnn } => format!("jump 0x{:03X}", nnn),
+        Call { nnn } => format!(":call 0x{:03X}", nnn),
+        SkipEqImm { x, nn } => format!("if v{:x} != 0x{:02X} then", x, nn),
+        SkipNeImm { x, nn } => format!("if v{:x} == 0x{:02X} then", x, nn),
+        SkipEq { x, y } => format!("if v{:x} != v{:x} then", x, y),
+        SaveRange { x, y } => format!("save v{:x} - v{:x}", x, y),
+        LoadRange { x, y } => format!("load v{:x} - v{:x}", x, y),
+        SetImm { x, nn } => format!("v{:x} := 0x{:02X}", x, nn),
+        AddImm { x, nn } => format!("v{:x} += 0x{:02X}", x, nn),
+        Set { x, y } => format!("v{:x} := v{:x}", x, y),
+        Or { x, y } => format!("v{:x} |= v{:x}", x, y),
+        And { x, y } => format!("v{:x} &= v{:x}", x, y),
+        Xor { x, y } => format!("v{:x} ^= v{:x}", x, y),
+        Add { x, y } => format!("v{:x} += v{:x}", x, y),
+        Sub { x, y } => format!("v{:x} -= v{:x}", x, y),
+        ShiftRight { x, y } => format!("v{:x} >>= v{:x}", x, y),
+        SubReverse { x, y } => format!("v{:x} =- v{:x}", x, y),
+        ShiftLeft { x, y } => format!("v{:x} <<= v{:x}", x, y),
+        SkipNe { x, y } => format!("if v{:x} == v{:x} then", x, y),
+        SetIndex { nnn } => format!("i := 0x{:03X}", nnn),
+        JumpOffset { nnn, .. } => format!("jump0 0x{:03X}", nnn),
+        Random { x, nn } => format!("v{:x} := random 0x{:02X}", x, nn),
+        Draw { x, y, n } => format!("sprite v{:x} v{:x} {}", x, y, n),
+        SkipKey { x } => format!("if v{:x} -key then", x),
+        SkipNotKey { x } => format!("if v{:x} key then", x),
+        SetIndexLong => "i := long".to_string(),
+        SelectPlanes { x } => format!("plane {}", x),
+        LoadAudio => "audio".to_string(),
+        GetDelay { x } => format!("v{:x} := delay", x),
+        WaitKey { x } => format!("v{:x} := key", x),
+        SetDelay { x } => format!("delay := v{:x}", x),
+        SetSound { x } => format!("buzzer := v{:x}", x),
+        AddIndex { x } => format!("i += v{:x}", x),
+        Font { x } => format!("i := hex v{:x}", x),
+        BigFont { x } => format!("i := bighex v{:x}", x),
+        Bcd { x } => format!("bcd v{:x}", x),
+        Pitch { x } => format!("pitch := v{:x}", x),
+        Store { x } => format!("save v{:x}", x),
+        Load { x } => format!("load v{:x}", x),
+        SaveFlags { x } => format!("saveflags v{:x}", x),
+        LoadFlags { x } => format!("loadflags v{:x}", x),
+    }
+}
+
+fn classic(instruction: &Instruction) -> String {
+    use Instruction::*;
+
+    match *instruction {
+        ScrollDown { n } => format!("SCD {}", n),
+        ScrollUp { n } => format!("SCU {}", n),
+        Clear => "CLS".to_string(),
+        Return => "RET".to_string(),
+        ScrollRight => "SCR".to_string(),
+        ScrollLeft => "SCL".to_string(),
+        Exit => "EXIT".to_string(),
+        LowRes => "LOW".to_string(),
+        HighRes => "HIGH".to_string(),
+        Jump { nnn } => format!("JP #{:03X}", nnn),
+        Call { nnn } => format!("CALL #{:03X}", nnn),
+        SkipEqImm { x, nn } => format!("SE V{:X}, #{:02X}", x, nn),
+        SkipNeImm { x, nn } => format!("SNE V{:X}, #{:02X}", x, nn),
+        SkipEq { x, y } => format!("SE V{:X}, V{:X}", x, y),
+        SaveRange { x, y } => format!("SAVE V{:X}, V{:X}", x, y),
+        LoadRange { x, y } => format!("LOAD V{:X}, V{:X}", x, y),
+        SetImm { x, nn } => format!("LD V{:X}, #{:02X}", x, nn),
+        AddImm { x, nn } => format!("ADD V{:X}, #{:02X}", x, nn),
+        Set { x, y } => format!("LD V{:X}, V{:X}", x, y),
+        Or { x, y } => format!("OR V{:X}, V{:X}", x, y),
+        And { x, y } => format!("AND V{:X}, V{:X}", x, y),
+        Xor { x, y } => format!("XOR V{:X}, V{:X}", x, y),
+        Add { x, y } => format!("ADD V{:X}, V{:X}", x, y),
+        Sub { x, y } => format!("SUB V{:X}, V{:X}", x, y),
+        ShiftRight { x, y } => format!("SHR V{:X}, V{:X}", x, y),
+        SubReverse { x, y } => format!("SUBN V{:X}, V{:X}", x, y),
+        ShiftLeft { x, y } => format!("SHL V{:X}, V{:X}", x, y),
+        SkipNe { x, y } => format!("SNE V{:X}, V{:X}", x, y),
+        SetIndex { nnn } => format!("LD I, #{:03X}", nnn),
+        JumpOffset { nnn, .. } => format!("JP V0, #{:03X}", nnn),
+        Random { x, nn } => format!("RND V{:X}, #{:02X}", x, nn),
+        Draw { x, y, n } => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        SkipKey { x } => format!("SKP V{:X}", x),
+        SkipNotKey { x } => format!("SKNP V{:X}", x),
+        SetIndexLong => "LD I, LONG".to_string(),
+        SelectPlanes { x } => format!("PLANE {}", x),
+        LoadAudio => "AUDIO".to_string(),
+        GetDelay { x } => format!("LD V{:X}, DT", x),
+        WaitKey { x } => format!("LD V{:X}, K", x),
+        SetDelay { x } => format!("LD DT, V{:X}", x),
+        SetSound { x } => format!("LD ST, V{:X}", x),
+        AddIndex { x } => format!("ADD I, V{:X}", x),
+        Font { x } => format!("LD F, V{:X}", x),
+        BigFont { x } => format!("LD HF, V{:X}", x),
+        Bcd { x } => format!("LD B, V{:X}", x),
+        Pitch { x } => format!("PITCH V{:X}", x),
+        Store { x } => format!("LD [I], V{:X}", x),
+        Load { x } => format!("LD V{:X}, [I]", x),
+        SaveFlags { x } => format!("LD R, V{:X}", x),
+        LoadFlags { x } => format!("LD V{:X}, R", x),
+    }
+}
+
+/// List `rom`, loaded at `load_address`, separating code from data.
+/// Starting from the load address, every path through jumps, calls
+/// and skips is followed, and what is reached is code. Computed
+/// jumps (BNNN) can't be followed, so their targets show up as data.
+/// The bytes that don't fit in memory, past 0xFFFF, are left out.
+pub fn listing(rom: &[u8], load_address: u16) -> Vec<Line> {
+    let start = load_address as usize;
+    let end = (start + rom.len()).min(MEMORY_SIZE);
+    let word = |address: usize| -> Option<u16> {
+        if address >= start && address + 1 < end {
+            Some(u16::from_be_bytes([
+                rom[address - start],
+                rom[address + 1 - start],
+            ]))
+        } else {
+            None
+        }
+    };
+
+    // Find the instructions reachable from the entry point
+    let mut code = vec![false; end - start];
+    let mut pending = vec![start];
+    while let Some(address) = pending.pop() {
+        if address < start || address >= end || code[address - start] {
+            continue;
+        }
+        let instruction = match word(address).map(decode) {
+            Some(Ok(instruction)) => instruction,
+            _ => continue,
+        };
+        let size = instruction.size() as usize;
+        if address + size > end {
+            continue;
+        }
+        code[address - start] = true;
+
+        let next = address + size;
+        match instruction {
+            Instruction::Jump { nnn } => pending.push(nnn as usize),
+            Instruction::Call { nnn } => pending.extend([nnn as usize, next]),
+            Instruction::Return | Instruction::Exit | Instruction::JumpOffset { .. } => (),
+            skip if skip.is_skip() => {
+                let skipped = match word(next) {
+                    Some(0xF000) => 4,
+                    _ => 2,
+                };
+                pending.extend([next, next + skipped]);
+            }
+            _ => pending.push(next),
+        }
+    }
+
+    // Walk through the ROM, skipping the bytes covered by instructions
+    let mut lines = Vec::new();
+    let mut address = start;
+    while address < end {
+        let is_code = code[address - start];
+        match word(address).map(decode) {
+            Some(Ok(instruction)) if is_code => {
+                let long = match instruction {
+                    Instruction::SetIndexLong => word(address + 2),
+                    _ => None,
+                };
+                lines.push(Line::Code {
+                    address: address as u16,
+                    opcode: instruction.encode(),
+                    instruction,
+                    long,
+                });
+                address += instruction.size() as usize;
+            }
+            _ => {
+                lines.push(Line::Data {
+                    address: address as u16,
+                    byte: rom[address - start],
+                });
+                address += 1;
+            }
+        }
+    }
+    lines
+}
+
+/// Format the `lines` of a listing, one per line, with
+/// their address and raw bytes.
+pub fn format_listing(lines: &[Line], syntax: Syntax) -> String {
+    let mut out = String::new();
+    for line in lines {
+        match *line {
+            Line::Code {
+                address,
+                opcode,
+                instruction,
+                long,
+            } => {
+                let mut text = format(&instruction, syntax);
+                let mut bytes = format!("{:04X}", opcode);
+                if let Some(long) = long {
+                    let _ = match syntax {
+                        Syntax::Octo => write!(text, " 0x{:04X}", long),
+                        Syntax::Classic => write!(text, " #{:04X}", long),
+                    };
+                    let _ = write!(bytes, "{:04X}", long);
+                }
+                let _ = writeln!(out, "0x{:04X}  {:<8}  {}", address, bytes, text);
+            }
+            Line::Data { address, byte } => {
+                let text = match syntax {
+                    Syntax::Octo => format!("0x{:02X}", byte),
+                    Syntax::Classic => format!("DB #{:02X}", byte),
+                };
+                let _ = writeln!(
+                    out,
+                    "0x{:04X}  {:<8}  {}",
+                    address,
+                    format!("{:02X}", byte),
+                    text
+                );
+            }
+        }
+    }
+    out
+}
@@ -1,78 +1,268 @@
-pub const WIDTH: usize = 64;
-pub const HEIGHT: usize = 32;
+use crate::error::StateError;
+use crate::state::{StateReader, StateWriter};
+
+/// Size of the screen in low resolution mode.
+pub const LORES_WIDTH: usize = 64;
+pub const LORES_HEIGHT: usize = 32;
+/// Size of the screen in SUPER-CHIP high resolution mode.
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
+/// Number of XO-CHIP bitplanes.
+pub const PLANES: usize = 2;
+
+// Size of one plane packed as bits.
+const PACKED_PLANE: usize = HIRES_WIDTH * HIRES_HEIGHT / 8;
 
 #[doc(hidden)]
 pub struct Display {
-    pub display: [bool; WIDTH * HEIGHT],
+    /// Pixels of the screen, row by row. Only the first
+    /// `width() * height()` are in use. Each pixel holds one bit
+    /// per plane, so its value is one of the 4 colors.
+    pub display: [u8; HIRES_WIDTH * HIRES_HEIGHT],
+    hires: bool,
+    // Bitmask of the planes drawn to, set by FN01.
+    planes: u8,
 }
 
 impl Display {
     pub fn new() -> Self {
         Display {
-            display: [false; WIDTH * HEIGHT],
+            display: [0; HIRES_WIDTH * HIRES_HEIGHT],
+            hires: false,
+            planes: 1,
+        }
+    }
+
+    /// Clear all planes, go back to low resolution
+    /// and select the first plane only.
+    pub fn reset(&mut self) {
+        self.display = [0; HIRES_WIDTH * HIRES_HEIGHT];
+        self.hires = false;
+        self.planes = 1;
+    }
+
+    /// Clear the selected planes.
+    pub fn clear(&mut self) {
+        let mask = !self.planes;
+        self.display.iter_mut().for_each(|pixel| *pixel &= mask);
+    }
+
+    /// Width of the screen in the current resolution.
+    pub fn width(&self) -> usize {
+        if self.hires {
+            HIRES_WIDTH
+        } else {
+            LORES_WIDTH
+        }
+    }
+
+    /// Height of the screen in the current resolution.
+    pub fn height(&self) -> usize {
+        if self.hires {
+            HIRES_HEIGHT
+        } else {
+            LORES_HEIGHT
         }
     }
 
-    pub fn clear(&mut self) -> () {
-        self.display = [false; WIDTH * HEIGHT];
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Switch between low and high resolution, which clears all planes.
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.display = [0; HIRES_WIDTH * HIRES_HEIGHT];
+    }
+
+    pub fn planes(&self) -> u8 {
+        self.planes
     }
 
-    fn at(&self, x: usize, y: usize) -> bool {
-        let x = x % WIDTH;
-        let y = y % HEIGHT;
-        self.display[y * WIDTH + x]
+    /// Select the planes affected by drawing, clearing and scrolling.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.planes = planes & 0b11;
     }
 
-    fn set(&mut self, x: usize, y: usize, val: bool) -> () {
-        let x = x % WIDTH;
-        let y = y % HEIGHT;
-        self.display[y * WIDTH + x] = val;
+    /// Number of selected planes.
+    pub fn plane_count(&self) -> usize {
+        self.planes.count_ones() as usize
+    }
+
+    fn at(&self, x: usize, y: usize) -> u8 {
+        let x = x % self.width();
+        let y = y % self.height();
+        self.display[y * self.width() + x]
+    }
+
+    fn set(&mut self, x: usize, y: usize, val: u8) {
+        let x = x % self.width();
+        let y = y % self.height();
+        let width = self.width();
+        self.display[y * width + x] = val;
+    }
+
+    /// Draw the 8 pixels wide `sprite` with its top left corner at (`x`, `y`),
+    /// which wrap around the screen. The parts of the sprite going past the
+    /// edges are dropped if `clip` is true, and wrap around otherwise.
+    /// When several planes are selected, `sprite` holds the data of
+    /// each plane one after the other.
+    /// Return true iff a pixel was turned off.
+    pub fn draw(&mut self, x: usize, y: usize, sprite: &[u8], clip: bool) -> bool {
+        self.draw_planes(x, y, sprite, 8, clip)
+    }
+
+    /// Same as [`Display::draw`], but for a 16x16 `sprite`
+    /// made of 32 bytes per plane, two per row.
+    pub fn draw_large(&mut self, x: usize, y: usize, sprite: &[u8], clip: bool) -> bool {
+        self.draw_planes(x, y, sprite, 16, clip)
+    }
+
+    fn draw_planes(&mut self, x: usize, y: usize, sprite: &[u8], width: usize, clip: bool) -> bool {
+        let count = self.plane_count();
+        if count == 0 {
+            return false;
+        }
+
+        let mut vf = false;
+        let planes = self.planes;
+        let selected = (0..PLANES as u8)
+            .map(|plane| 1 << plane)
+            .filter(|bit| planes & bit != 0);
+        for (bit, data) in selected.zip(sprite.chunks(sprite.len() / count)) {
+            vf |= self.blit(x, y, data, width, bit, clip);
+        }
+        vf
     }
 
-    pub fn draw(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+    fn blit(
+        &mut self,
+        x: usize,
+        y: usize,
+        sprite: &[u8],
+        width: usize,
+        bit: u8,
+        clip: bool,
+    ) -> bool {
         let mut vf = false;
-        let rows = sprite.len();
+        let x = x % self.width();
+        let y = y % self.height();
 
-        for i in 0..rows {
-            let row = sprite[i];
+        for (i, row) in sprite.chunks(width / 8).enumerate() {
             let yi = y + i;
-            for col in 0..8 {
-                let pixel = (row & (0b10000000 >> col)) != 0;
+            if clip && yi >= self.height() {
+                break;
+            }
+            for col in 0..width {
                 let xi = x + col;
+                if clip && xi >= self.width() {
+                    break;
+                }
+                let pixel = (row[col / 8] & (0b10000000 >> (col % 8))) != 0;
                 if pixel {
                     let old_val = self.at(xi, yi);
-                    self.set(xi, yi, pixel ^ old_val);
-                    if old_val {
+                    self.set(xi, yi, old_val ^ bit);
+                    if old_val & bit != 0 {
                         vf = true;
                     }
                 }
-                if xi == WIDTH - 1 {
-                    break;
-                }
             }
+        }
+
+        vf
+    }
+
+    /// Scroll the selected planes `n` pixels up.
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll(0, -(n as isize));
+    }
+
+    /// Scroll the selected planes `n` pixels down.
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll(0, n as isize);
+    }
+
+    /// Scroll the selected planes `n` pixels to the left.
+    pub fn scroll_left(&mut self, n: usize) {
+        self.scroll(-(n as isize), 0);
+    }
 
-            if yi == HEIGHT - 1 {
-                return vf;
+    /// Scroll the selected planes `n` pixels to the right.
+    pub fn scroll_right(&mut self, n: usize) {
+        self.scroll(n as isize, 0);
+    }
+
+    // Move the selected planes by (`dx`, `dy`), what comes
+    // from outside of the screen is blank.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let old = self.display;
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&sx) && (0..height).contains(&sy) {
+                    old[(sy * width + sx) as usize]
+                } else {
+                    0
+                };
+                let pixel = &mut self.display[(y * width + x) as usize];
+                *pixel = (*pixel & !self.planes) | (moved & self.planes);
             }
         }
+    }
 
-        vf
+    /// Pixels of the screen in the current resolution, row by row.
+    pub fn get(&self) -> &[u8] {
+        &self.display[..self.width() * self.height()]
     }
 
-    pub fn get(&self) -> &[bool; WIDTH * HEIGHT] {
-        &self.display
+    pub(crate) fn save(&self, state: &mut StateWriter) {
+        state.bool(self.hires);
+        state.u8(self.planes);
+        let mut packed = [0; PLANES * PACKED_PLANE];
+        for (i, pixel) in self.display.iter().enumerate() {
+            for plane in 0..PLANES {
+                if pixel & (1 << plane) != 0 {
+                    packed[plane * PACKED_PLANE + i / 8] |= 0x80 >> (i % 8);
+                }
+            }
+        }
+        state.packed(&packed);
     }
 
+    pub(crate) fn load(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.hires = state.bool()?;
+        self.planes = state.u8()?;
+        if self.planes > 0b11 {
+            return Err(StateError::Corrupted);
+        }
+        let mut packed = [0; PLANES * PACKED_PLANE];
+        state.packed(&mut packed)?;
+        for (i, pixel) in self.display.iter_mut().enumerate() {
+            *pixel = (0..PLANES)
+                .filter(|plane| packed[plane * PACKED_PLANE + i / 8] & (0x80 >> (i % 8)) != 0)
+                .fold(0, |pixel, plane| pixel | 1 << plane);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
     pub fn debug_draw(&self) {
-        for x in 0..WIDTH {
-            for y in 0..HEIGHT {
-                if self.at(x, y) {
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.at(x, y) != 0 {
                     print!("#");
                 } else {
                     print!(" ");
                 }
             }
-            println!("");
+            println!();
         }
     }
 }
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
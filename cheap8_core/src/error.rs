@@ -0,0 +1,156 @@
+use core::error::Error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Error raised by the [`Cpu`](crate::Cpu) while running a ROM.
+///
+/// Every variant carries the `address` of the faulty instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    /// The `opcode` is not a known instruction.
+    UnknownOpcode { address: u16, opcode: u16 },
+    /// A subroutine was called while the stack was full.
+    StackOverflow { address: u16 },
+    /// A subroutine returned while the stack was empty.
+    StackUnderflow { address: u16 },
+    /// The instruction tried to access memory at `access`,
+    /// which is outside of the RAM.
+    MemoryOutOfRange { address: u16, access: usize },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnknownOpcode { address, opcode } => {
+                write!(f, "unknown opcode {:#06x} at {:#05x}", opcode, address)
+            }
+            CpuError::StackOverflow { address } => {
+                write!(f, "stack overflow at {:#05x}", address)
+            }
+            CpuError::StackUnderflow { address } => {
+                write!(f, "stack underflow at {:#05x}", address)
+            }
+            CpuError::MemoryOutOfRange { address, access } => write!(
+                f,
+                "out of range memory access to {:#x} at {:#05x}",
+                access, address
+            ),
+        }
+    }
+}
+
+impl Error for CpuError {}
+
+/// Error raised when a ROM can't be loaded into memory.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The ROM couldn't be read.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+    /// The ROM is `size` bytes long, but only `max` bytes fit in memory.
+    RomTooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::Io(e) => write!(f, "couldn't read the ROM: {}", e),
+            LoadError::RomTooLarge { size, max } => write!(
+                f,
+                "the ROM is {} bytes long, but at most {} bytes fit in memory",
+                size, max
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            LoadError::Io(e) => Some(e),
+            LoadError::RomTooLarge { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+/// Error raised when a save state can't be restored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The data is not a save state.
+    NotAState,
+    /// The save state was written in an unsupported format `version`.
+    UnsupportedVersion { version: u8 },
+    /// The save state was taken with another ROM, `expected` is the hash
+    /// of the ROM currently loaded and `found` the one in the save state.
+    RomMismatch { expected: u64, found: u64 },
+    /// The save state is truncated or holds invalid values.
+    Corrupted,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "not a save state"),
+            StateError::UnsupportedVersion { version } => {
+                write!(f, "unsupported save state version {}", version)
+            }
+            StateError::RomMismatch { expected, found } => write!(
+                f,
+                "the save state was taken with ROM {:016x}, but ROM {:016x} is loaded",
+                found, expected
+            ),
+            StateError::Corrupted => write!(f, "the save state is corrupted"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+/// Error raised when a movie can't be read or played.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieError {
+    /// The data is not a movie.
+    NotAMovie,
+    /// The movie was written in an unsupported format `version`.
+    UnsupportedVersion { version: u8 },
+    /// The movie was recorded with another ROM, `expected` is the hash
+    /// of the ROM currently loaded and `found` the one in the movie.
+    RomMismatch { expected: u64, found: u64 },
+    /// The movie is truncated or holds invalid values.
+    Corrupted,
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovieError::NotAMovie => write!(f, "not a movie"),
+            MovieError::UnsupportedVersion { version } => {
+                write!(f, "unsupported movie version {}", version)
+            }
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "the movie was recorded with ROM {:016x}, but ROM {:016x} is loaded",
+                found, expected
+            ),
+            MovieError::Corrupted => write!(f, "the movie is corrupted"),
+        }
+    }
+}
+
+impl Error for MovieError {}
+
+impl From<StateError> for MovieError {
+    fn from(_: StateError) -> Self {
+        MovieError::Corrupted
+    }
+}
@@ -0,0 +1,282 @@
+use core::error::Error;
+use core::fmt;
+
+/// A decoded instruction, with its operands extracted from the opcode.
+///
+/// `x` and `y` are register numbers, `n` a 4 bits immediate,
+/// `nn` a byte and `nnn` an address. It covers CHIP-8 and the
+/// SUPER-CHIP 1.1 and XO-CHIP extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 00CN: scroll the screen N pixels down (SUPER-CHIP).
+    ScrollDown { n: u8 },
+    /// 00DN: scroll the screen N pixels up (XO-CHIP).
+    ScrollUp { n: u8 },
+    /// 00E0: clear the screen.
+    Clear,
+    /// 00EE: return from a subroutine.
+    Return,
+    /// 00FB: scroll the screen 4 pixels right (SUPER-CHIP).
+    ScrollRight,
+    /// 00FC: scroll the screen 4 pixels left (SUPER-CHIP).
+    ScrollLeft,
+    /// 00FD: exit the program (SUPER-CHIP).
+    Exit,
+    /// 00FE: switch to low resolution (SUPER-CHIP).
+    LowRes,
+    /// 00FF: switch to high resolution (SUPER-CHIP).
+    HighRes,
+    /// 1NNN: jump to NNN.
+    Jump { nnn: u16 },
+    /// 2NNN: call the subroutine at NNN.
+    Call { nnn: u16 },
+    /// 3XNN: skip the next instruction if VX == NN.
+    SkipEqImm { x: u8, nn: u8 },
+    /// 4XNN: skip the next instruction if VX != NN.
+    SkipNeImm { x: u8, nn: u8 },
+    /// 5XY0: skip the next instruction if VX == VY.
+    SkipEq { x: u8, y: u8 },
+    /// 5XY2: store VX to VY in memory at I (XO-CHIP).
+    SaveRange { x: u8, y: u8 },
+    /// 5XY3: load VX to VY from memory at I (XO-CHIP).
+    LoadRange { x: u8, y: u8 },
+    /// 6XNN: VX = NN.
+    SetImm { x: u8, nn: u8 },
+    /// 7XNN: VX += NN, without carry.
+    AddImm { x: u8, nn: u8 },
+    /// 8XY0: VX = VY.
+    Set { x: u8, y: u8 },
+    /// 8XY1: VX |= VY.
+    Or { x: u8, y: u8 },
+    /// 8XY2: VX &= VY.
+    And { x: u8, y: u8 },
+    /// 8XY3: VX ^= VY.
+    Xor { x: u8, y: u8 },
+    /// 8XY4: VX += VY, VF is the carry.
+    Add { x: u8, y: u8 },
+    /// 8XY5: VX -= VY, VF is the absence of borrow.
+    Sub { x: u8, y: u8 },
+    /// 8XY6: VX = VX (or VY) >> 1, VF is the bit shifted out.
+    ShiftRight { x: u8, y: u8 },
+    /// 8XY7: VX = VY - VX, VF is the absence of borrow.
+    SubReverse { x: u8, y: u8 },
+    /// 8XYE: VX = VX (or VY) << 1, VF is the bit shifted out.
+    ShiftLeft { x: u8, y: u8 },
+    /// 9XY0: skip the next instruction if VX != VY.
+    SkipNe { x: u8, y: u8 },
+    /// ANNN: I = NNN.
+    SetIndex { nnn: u16 },
+    /// BNNN: jump to NNN + V0 (or NNN + VX, X being
+    /// the highest nibble of NNN).
+    JumpOffset { x: u8, nnn: u16 },
+    /// CXNN: VX = random byte & NN.
+    Random { x: u8, nn: u8 },
+    /// DXYN: draw the N rows sprite at I at (VX, VY),
+    /// a 16x16 sprite if N is 0 (SUPER-CHIP).
+    Draw { x: u8, y: u8, n: u8 },
+    /// EX9E: skip the next instruction if key VX is pressed.
+    SkipKey { x: u8 },
+    /// EXA1: skip the next instruction if key VX is not pressed.
+    SkipNotKey { x: u8 },
+    /// F000 NNNN: I = the 16 bits address that follows (XO-CHIP).
+    SetIndexLong,
+    /// FX01: draw to the planes of bitmask X (XO-CHIP).
+    SelectPlanes { x: u8 },
+    /// F002: load the 16 bytes at I in the audio pattern buffer (XO-CHIP).
+    LoadAudio,
+    /// FX07: VX = delay timer.
+    GetDelay { x: u8 },
+    /// FX0A: wait for a key press and store it in VX.
+    WaitKey { x: u8 },
+    /// FX15: delay timer = VX.
+    SetDelay { x: u8 },
+    /// FX18: sound timer = VX.
+    SetSound { x: u8 },
+    /// FX1E: I += VX.
+    AddIndex { x: u8 },
+    /// FX29: I = address of the font sprite of digit VX.
+    Font { x: u8 },
+    /// FX30: I = address of the big font sprite of digit VX (SUPER-CHIP).
+    BigFont { x: u8 },
+    /// FX33: store the 3 decimal digits of VX in memory at I.
+    Bcd { x: u8 },
+    /// FX3A: pitch = VX (XO-CHIP).
+    Pitch { x: u8 },
+    /// FX55: store V0 to VX in memory at I.
+    Store { x: u8 },
+    /// FX65: load V0 to VX from memory at I.
+    Load { x: u8 },
+    /// FX75: store V0 to VX in the user flags (SUPER-CHIP).
+    SaveFlags { x: u8 },
+    /// FX85: load V0 to VX from the user flags (SUPER-CHIP).
+    LoadFlags { x: u8 },
+}
+
+/// Error raised when decoding a word that is not an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    pub opcode: u16,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown opcode {:#06x}", self.opcode)
+    }
+}
+
+impl Error for DecodeError {}
+
+/// Decode the `opcode` word into an [`Instruction`].
+pub fn decode(opcode: u16) -> Result<Instruction, DecodeError> {
+    use Instruction::*;
+
+    let x = ((opcode & 0x0F00) >> 8) as u8;
+    let y = ((opcode & 0x00F0) >> 4) as u8;
+    let n = (opcode & 0x000F) as u8;
+    let nn = (opcode & 0x00FF) as u8;
+    let nnn = opcode & 0x0FFF;
+
+    let instruction = match (opcode >> 12, x, y, n) {
+        (0x0, 0x0, 0xC, _) => ScrollDown { n },
+        (0x0, 0x0, 0xD, _) => ScrollUp { n },
+        (0x0, 0x0, 0xE, 0x0) => Clear,
+        (0x0, 0x0, 0xE, 0xE) => Return,
+        (0x0, 0x0, 0xF, 0xB) => ScrollRight,
+        (0x0, 0x0, 0xF, 0xC) => ScrollLeft,
+        (0x0, 0x0, 0xF, 0xD) => Exit,
+        (0x0, 0x0, 0xF, 0xE) => LowRes,
+        (0x0, 0x0, 0xF, 0xF) => HighRes,
+        (0x1, _, _, _) => Jump { nnn },
+        (0x2, _, _, _) => Call { nnn },
+        (0x3, _, _, _) => SkipEqImm { x, nn },
+        (0x4, _, _, _) => SkipNeImm { x, nn },
+        (0x5, _, _, 0x0) => SkipEq { x, y },
+        (0x5, _, _, 0x2) => SaveRange { x, y },
+        (0x5, _, _, 0x3) => LoadRange { x, y },
+        (0x6, _, _, _) => SetImm { x, nn },
+        (0x7, _, _, _) => AddImm { x, nn },
+        (0x8, _, _, 0x0) => Set { x, y },
+        (0x8, _, _, 0x1) => Or { x, y },
+        (0x8, _, _, 0x2) => And { x, y },
+        (0x8, _, _, 0x3) => Xor { x, y },
+        (0x8, _, _, 0x4) => Add { x, y },
+        (0x8, _, _, 0x5) => Sub { x, y },
+        (0x8, _, _, 0x6) => ShiftRight { x, y },
+        (0x8, _, _, 0x7) => SubReverse { x, y },
+        (0x8, _, _, 0xE) => ShiftLeft { x, y },
+        (0x9, _, _, 0x0) => SkipNe { x, y },
+        (0xA, _, _, _) => SetIndex { nnn },
+        (0xB, _, _, _) => JumpOffset { x, nnn },
+        (0xC, _, _, _) => Random { x, nn },
+        (0xD, _, _, _) => Draw { x, y, n },
+        (0xE, _, 0x9, 0xE) => SkipKey { x },
+        (0xE, _, 0xA, 0x1) => SkipNotKey { x },
+        (0xF, 0x0, 0x0, 0x0) => SetIndexLong,
+        (0xF, _, 0x0, 0x1) => SelectPlanes { x },
+        (0xF, 0x0, 0x0, 0x2) => LoadAudio,
+        (0xF, _, 0x0, 0x7) => GetDelay { x },
+        (0xF, _, 0x0, 0xA) => WaitKey { x },
+        (0xF, _, 0x1, 0x5) => SetDelay { x },
+        (0xF, _, 0x1, 0x8) => SetSound { x },
+        (0xF, _, 0x1, 0xE) => AddIndex { x },
+        (0xF, _, 0x2, 0x9) => Font { x },
+        (0xF, _, 0x3, 0x0) => BigFont { x },
+        (0xF, _, 0x3, 0x3) => Bcd { x },
+        (0xF, _, 0x3, 0xA) => Pitch { x },
+        (0xF, _, 0x5, 0x5) => Store { x },
+        (0xF, _, 0x6, 0x5) => Load { x },
+        (0xF, _, 0x7, 0x5) => SaveFlags { x },
+        (0xF, _, 0x8, 0x5) => LoadFlags { x },
+        _ => return Err(DecodeError { opcode }),
+    };
+
+    Ok(instruction)
+}
+
+impl Instruction {
+    /// Opcode word of the instruction, the inverse of [`decode`].
+    pub fn encode(&self) -> u16 {
+        use Instruction::*;
+
+        let xy = |op: u16, x: u8, y: u8, n: u16| op << 12 | (x as u16) << 8 | (y as u16) << 4 | n;
+        let xnn = |op: u16, x: u8, nn: u8| op << 12 | (x as u16) << 8 | nn as u16;
+        let fx = |x: u8, nn: u16| 0xF000 | (x as u16) << 8 | nn;
+
+        match *self {
+            ScrollDown { n } => 0x00C0 | n as u16,
+            ScrollUp { n } => 0x00D0 | n as u16,
+            Clear => 0x00E0,
+            Return => 0x00EE,
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            LowRes => 0x00FE,
+            HighRes => 0x00FF,
+            Jump { nnn } => 0x1000 | nnn,
+            Call { nnn } => 0x2000 | nnn,
+            SkipEqImm { x, nn } => xnn(0x3, x, nn),
+            SkipNeImm { x, nn } => xnn(0x4, x, nn),
+            SkipEq { x, y } => xy(0x5, x, y, 0x0),
+            SaveRange { x, y } => xy(0x5, x, y, 0x2),
+            LoadRange { x, y } => xy(0x5, x, y, 0x3),
+            SetImm { x, nn } => xnn(0x6, x, nn),
+            AddImm { x, nn } => xnn(0x7, x, nn),
+            Set { x, y } => xy(0x8, x, y, 0x0),
+            Or { x, y } => xy(0x8, x, y, 0x1),
+            And { x, y } => xy(0x8, x, y, 0x2),
+            Xor { x, y } => xy(0x8, x, y, 0x3),
+            Add { x, y } => xy(0x8, x, y, 0x4),
+            Sub { x, y } => xy(0x8, x, y, 0x5),
+            ShiftRight { x, y } => xy(0x8, x, y, 0x6),
+            SubReverse { x, y } => xy(0x8, x, y, 0x7),
+            ShiftLeft { x, y } => xy(0x8, x, y, 0xE),
+            SkipNe { x, y } => xy(0x9, x, y, 0x0),
+            SetIndex { nnn } => 0xA000 | nnn,
+            JumpOffset { nnn, .. } => 0xB000 | nnn,
+            Random { x, nn } => xnn(0xC, x, nn),
+            Draw { x, y, n } => xy(0xD, x, y, n as u16),
+            SkipKey { x } => xnn(0xE, x, 0x9E),
+            SkipNotKey { x } => xnn(0xE, x, 0xA1),
+            SetIndexLong => 0xF000,
+            SelectPlanes { x } => fx(x, 0x01),
+            LoadAudio => 0xF002,
+            GetDelay { x } => fx(x, 0x07),
+            WaitKey { x } => fx(x, 0x0A),
+            SetDelay { x } => fx(x, 0x15),
+            SetSound { x } => fx(x, 0x18),
+            AddIndex { x } => fx(x, 0x1E),
+            Font { x } => fx(x, 0x29),
+            BigFont { x } => fx(x, 0x30),
+            Bcd { x } => fx(x, 0x33),
+            Pitch { x } => fx(x, 0x3A),
+            Store { x } => fx(x, 0x55),
+            Load { x } => fx(x, 0x65),
+            SaveFlags { x } => fx(x, 0x75),
+            LoadFlags { x } => fx(x, 0x85),
+        }
+    }
+
+    /// Size of the instruction in bytes, 4 for [`Instruction::SetIndexLong`]
+    /// which is followed by its address, 2 otherwise.
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::SetIndexLong => 4,
+            _ => 2,
+        }
+    }
+
+    /// True iff the instruction may skip the next one.
+    pub fn is_skip(&self) -> bool {
+        use Instruction::*;
+
+        matches!(
+            self,
+            SkipEqImm { .. }
+                | SkipNeImm { .. }
+                | SkipEq { .. }
+                | SkipNe { .. }
+                | SkipKey { .. }
+                | SkipNotKey { .. }
+        )
+    }
+}
@@ -2,9 +2,33 @@
 //! This is the core crate of cheap8, it
 //! contains all strucs and implementation to
 //! have a chip8 interpreter (internally).
+//!
+//! Without the default `std` feature it is `no_std`, only needing
+//! `alloc`: ROMs are then loaded with [`Cpu::load_bytes`] and
+#![cfg_attr(feature = "std", doc = " there is no [`trace`] module.")]
+#![cfg_attr(not(feature = "std"), doc = " there is no `trace` module.")]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 mod cpu;
+pub mod debugger;
+pub mod disasm;
 mod display;
+mod error;
+mod instruction;
+pub mod movie;
+mod quirks;
+pub mod rewind;
+mod rng;
+mod state;
 mod timer;
+#[cfg(feature = "std")]
+pub mod trace;
 
-pub use cpu::{Cpu, Output};
-pub use display::{Display, HEIGHT, WIDTH};
+pub use cpu::{Cpu, Output, DEFAULT_LOAD_ADDRESS};
+pub use display::{Display, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH, PLANES};
+pub use error::{CpuError, LoadError, MovieError, StateError};
+pub use instruction::{decode, DecodeError, Instruction};
+pub use quirks::Quirks;
+pub use state::rom_hash;
@@ -0,0 +1,186 @@
+//! Recordings of the keys pressed every frame, replayed to
+//! reproduce a run exactly.
+//!
+//! A movie starts with `C8MV` and a version, like the save states,
+//! then holds the settings the run depends on (ROM hash, seed,
+//! quirks, instructions per frame and load address) and the keys of
+//! each frame, as 16 bits masks compressed with the save state packing.
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use crate::cpu::Cpu;
+use crate::error::MovieError;
+use crate::quirks::Quirks;
+use crate::state::{StateReader, StateWriter};
+
+const MAGIC: &[u8; 4] = b"C8MV";
+const VERSION: u8 = 1;
+
+/// Keys pressed on every frame of a run, and what is needed to replay it:
+/// a [`Cpu`] set up by [`Movie::cpu`] and given the ROM, then run one
+/// frame at a time with the keys of [`Movie::keys`], ends up in the same
+/// state as the CPU that was recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub quirks: Quirks,
+    pub ipf: usize,
+    pub load_address: u16,
+    // Keys pressed on each frame, one bit per key.
+    frames: Vec<u16>,
+}
+
+impl Movie {
+    /// Start recording a run of `cpu`, which has its ROM loaded
+    /// and was just reset, executing `ipf` instructions per frame.
+    pub fn new(cpu: &Cpu, ipf: usize) -> Self {
+        Movie {
+            rom_hash: cpu.rom_hash(),
+            seed: cpu.seed(),
+            quirks: cpu.quirks(),
+            ipf,
+            load_address: cpu.load_address(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Number of frames recorded.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Record the keys pressed on `frame`, usually [`Cpu::frames`] before
+    /// running it. The frames after it are dropped, so that rewinding
+    /// and running again replaces them.
+    pub fn record(&mut self, frame: u64, keys: &[bool; 16]) {
+        let mask = keys
+            .iter()
+            .enumerate()
+            .filter(|(_, pressed)| **pressed)
+            .fold(0, |mask, (key, _)| mask | 1 << key);
+        self.frames.truncate(frame as usize);
+        self.frames.resize(frame as usize, 0);
+        self.frames.push(mask);
+    }
+
+    /// Keys pressed on `frame`, `None` past the end of the movie.
+    pub fn keys(&self, frame: u64) -> Option<[bool; 16]> {
+        let mask = *self.frames.get(usize::try_from(frame).ok()?)?;
+        let mut keys = [false; 16];
+        for (key, pressed) in keys.iter_mut().enumerate() {
+            *pressed = mask & 1 << key != 0;
+        }
+        Some(keys)
+    }
+
+    /// CPU set up as the recorded one, before loading the ROM.
+    pub fn cpu(&self) -> Cpu {
+        let mut cpu = Cpu::with_seed(self.quirks, self.seed);
+        cpu.set_load_address(self.load_address);
+        cpu.reset();
+        cpu
+    }
+
+    /// Fail if the ROM loaded in `cpu` isn't the one of the movie.
+    pub fn check_rom(&self, cpu: &Cpu) -> Result<(), MovieError> {
+        if cpu.rom_hash() != self.rom_hash {
+            return Err(MovieError::RomMismatch {
+                expected: cpu.rom_hash(),
+                found: self.rom_hash,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut movie = StateWriter::new();
+        movie.bytes(MAGIC);
+        movie.u8(VERSION);
+        movie.u64(self.rom_hash);
+        movie.u64(self.seed);
+        movie.u8(quirks_to_bits(self.quirks));
+        movie.u64(self.ipf as u64);
+        movie.u16(self.load_address);
+        movie.u64(self.frames.len() as u64);
+        let keys: Vec<u8> = self
+            .frames
+            .iter()
+            .flat_map(|mask| mask.to_le_bytes())
+            .collect();
+        movie.packed(&keys);
+        movie.into_bytes()
+    }
+
+    /// Read a movie written by [`Movie::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        let mut movie = StateReader::new(bytes);
+        if movie.bytes(MAGIC.len()) != Ok(MAGIC) {
+            return Err(MovieError::NotAMovie);
+        }
+        let version = movie.u8()?;
+        if version != VERSION {
+            return Err(MovieError::UnsupportedVersion { version });
+        }
+        let rom_hash = movie.u64()?;
+        let seed = movie.u64()?;
+        let quirks = quirks_from_bits(movie.u8()?).ok_or(MovieError::Corrupted)?;
+        let ipf = usize::try_from(movie.u64()?).map_err(|_| MovieError::Corrupted)?;
+        let load_address = movie.u16()?;
+        let len = usize::try_from(movie.u64()?).map_err(|_| MovieError::Corrupted)?;
+        // Don't trust the length to allocate, the keys
+        // are packed in at most a few bytes per frame
+        if len > bytes.len().saturating_mul(1 << 16) {
+            return Err(MovieError::Corrupted);
+        }
+        let mut keys = vec![0; len * 2];
+        movie.packed(&mut keys)?;
+        movie.finish()?;
+
+        Ok(Movie {
+            rom_hash,
+            seed,
+            quirks,
+            ipf,
+            load_address,
+            frames: keys
+                .chunks(2)
+                .map(|mask| u16::from_le_bytes([mask[0], mask[1]]))
+                .collect(),
+        })
+    }
+}
+
+fn quirks_to_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.shift_vy,
+        quirks.load_store_increment,
+        quirks.jump_vx,
+        quirks.vf_reset,
+        quirks.clip_sprites,
+        quirks.display_wait,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, quirk)| bits | (*quirk as u8) << i)
+}
+
+fn quirks_from_bits(bits: u8) -> Option<Quirks> {
+    if bits >> 6 != 0 {
+        return None;
+    }
+    let quirk = |i: u8| bits & 1 << i != 0;
+    Some(Quirks {
+        shift_vy: quirk(0),
+        load_store_increment: quirk(1),
+        jump_vx: quirk(2),
+        vf_reset: quirk(3),
+        clip_sprites: quirk(4),
+        display_wait: quirk(5),
+    })
+}
@@ -0,0 +1,72 @@
+/// Behaviors of the instructions that differ between the
+/// CHIP-8 interpreters. Most ROMs only run correctly with
+/// the behaviors of the interpreter they were written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8XY6 and 8XYE shift VY and store the result in VX,
+    /// instead of shifting VX in place.
+    pub shift_vy: bool,
+    /// FX55 and FX65 increment I by X + 1.
+    pub load_store_increment: bool,
+    /// BNNN jumps to NNN + VX instead of NNN + V0.
+    pub jump_vx: bool,
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0.
+    pub vf_reset: bool,
+    /// Sprites are clipped at the edges of the screen,
+    /// instead of wrapping around.
+    pub clip_sprites: bool,
+    /// DXYN waits for the next vertical blank (timer tick) before drawing,
+    /// so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    /// Behaviors of the original interpreter of the COSMAC VIP.
+    pub fn cosmac_vip() -> Self {
+        Quirks {
+            shift_vy: true,
+            load_store_increment: true,
+            jump_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            display_wait: true,
+        }
+    }
+
+    /// Behaviors of CHIP-48, on the HP-48 calculators.
+    pub fn chip48() -> Self {
+        Quirks {
+            shift_vy: false,
+            load_store_increment: false,
+            jump_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            display_wait: false,
+        }
+    }
+
+    /// Behaviors of SUPER-CHIP 1.1, the successor of CHIP-48.
+    /// They are the same as CHIP-48 for the instructions both share.
+    pub fn super_chip() -> Self {
+        Self::chip48()
+    }
+
+    /// Behaviors of modern interpreters like Octo, expected
+    /// by most of the recent ROMs.
+    pub fn modern() -> Self {
+        Quirks {
+            shift_vy: true,
+            load_store_increment: true,
+            jump_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            display_wait: false,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::modern()
+    }
+}
@@ -0,0 +1,97 @@
+//! Going back in time, by restoring the states of the machine
+//! recorded at the end of the previous frames.
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::cpu::Cpu;
+use crate::error::StateError;
+use crate::state::{pack, unpack};
+
+/// Bounded history of the states of a [`Cpu`]. Only the latest state is
+/// kept in full, each older one is stored as its difference with the
+/// next one, compressed, which is a few bytes for most frames.
+pub struct Rewind {
+    capacity: usize,
+    // Latest state, written by `Cpu::snapshot`.
+    latest: Option<Vec<u8>>,
+    // XOR of each state with the next one, packed, oldest first.
+    deltas: VecDeque<Vec<u8>>,
+}
+
+impl Rewind {
+    /// Create a history keeping up to `capacity` states
+    /// before the latest one.
+    pub fn new(capacity: usize) -> Self {
+        Rewind {
+            capacity,
+            latest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of states before the latest one, that
+    /// is how many times [`Rewind::step_back`] succeeds.
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// Forget all the states, after a reset or loading another ROM.
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+    }
+
+    /// Record the state of `cpu`, usually at the end of every frame.
+    /// The oldest state is dropped when the history is full, and
+    /// nothing is recorded when it keeps no state.
+    pub fn push(&mut self, cpu: &Cpu) {
+        if self.capacity == 0 {
+            return;
+        }
+        let state = cpu.snapshot();
+        if let Some(latest) = &self.latest {
+            let mut delta = state.clone();
+            xor(&mut delta, latest);
+            self.deltas.push_back(pack(&delta));
+            if self.deltas.len() > self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+        self.latest = Some(state);
+    }
+
+    /// Restore `cpu` to the state recorded before the latest one, which
+    /// becomes the latest. Return false if there is none, leaving `cpu`
+    /// untouched.
+    pub fn step_back(&mut self, cpu: &mut Cpu) -> Result<bool, StateError> {
+        let (latest, delta) = match (&self.latest, self.deltas.back()) {
+            (Some(latest), Some(delta)) => (latest, delta),
+            _ => return Ok(false),
+        };
+        let mut previous = vec![0; latest.len()];
+        unpack(delta, &mut previous).ok_or(StateError::Corrupted)?;
+        xor(&mut previous, latest);
+        cpu.restore(&previous)?;
+
+        self.deltas.pop_back();
+        self.latest = Some(previous);
+        Ok(true)
+    }
+}
+
+// XOR `other` into `bytes`, the states all having the same size.
+fn xor(bytes: &mut [u8], other: &[u8]) {
+    bytes
+        .iter_mut()
+        .zip(other)
+        .for_each(|(byte, other)| *byte ^= other);
+}
@@ -0,0 +1,27 @@
+/// Small pseudo random generator (SplitMix64), whose whole
+/// state is a `u64` so it can be saved along the CPU.
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(state: u64) -> Self {
+        Rng { state }
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u64() >> 56) as u8
+    }
+}
@@ -0,0 +1,286 @@
+//! Binary encoding of the save states.
+//!
+//! A save state starts with a header made of [`MAGIC`], the
+//! format [`VERSION`] and the hash of the ROM it was taken with,
+//! followed by the state of each component of the machine.
+//! Integers are little endian, and the large, mostly empty,
+//! buffers are compressed with [`pack`].
+use alloc::vec::Vec;
+
+use crate::error::StateError;
+
+pub(crate) const MAGIC: &[u8; 4] = b"C8ST";
+pub(crate) const VERSION: u8 = 3;
+
+/// Hash identifying a ROM (64 bits FNV-1a), stored in
+/// the save states to check they match the loaded ROM.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub(crate) struct StateWriter {
+    buffer: Vec<u8>,
+    // False to write the large buffers as is, so that all
+    // the states have the same layout.
+    compress: bool,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        StateWriter {
+            buffer: Vec::new(),
+            compress: true,
+        }
+    }
+
+    /// Writer leaving the buffers given to [`StateWriter::packed`]
+    /// uncompressed, for states that are compared with each other.
+    pub fn uncompressed() -> Self {
+        StateWriter {
+            buffer: Vec::new(),
+            compress: false,
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Write `bytes` compressed, prefixed by the compressed length.
+    pub fn packed(&mut self, bytes: &[u8]) {
+        if !self.compress {
+            self.bytes(bytes);
+            return;
+        }
+        let packed = pack(bytes);
+        write_varint(&mut self.buffer, packed.len());
+        self.bytes(&packed);
+    }
+}
+
+pub(crate) struct StateReader<'a> {
+    buffer: &'a [u8],
+    compress: bool,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        StateReader {
+            buffer,
+            compress: true,
+        }
+    }
+
+    /// Reader of the states written by [`StateWriter::uncompressed`].
+    pub fn uncompressed(buffer: &'a [u8]) -> Self {
+        StateReader {
+            buffer,
+            compress: false,
+        }
+    }
+
+    /// Fail if there are bytes left to read.
+    pub fn finish(self) -> Result<(), StateError> {
+        if self.buffer.is_empty() {
+            Ok(())
+        } else {
+            Err(StateError::Corrupted)
+        }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.buffer.len() < len {
+            return Err(StateError::Corrupted);
+        }
+        let (bytes, rest) = self.buffer.split_at(len);
+        self.buffer = rest;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Corrupted),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, StateError> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, StateError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Read bytes written by [`StateWriter::packed`] into `out`,
+    /// which must be exactly as long as the original bytes.
+    pub fn packed(&mut self, out: &mut [u8]) -> Result<(), StateError> {
+        self.packed_slice(out.len())?.unpack(out);
+        Ok(())
+    }
+
+    /// Same as [`StateReader::packed`], but only check the bytes
+    /// are `len` bytes long once decompressed, to decompress them
+    /// later, after reading the rest of the state.
+    pub fn packed_slice(&mut self, len: usize) -> Result<Packed<'a>, StateError> {
+        if !self.compress {
+            return Ok(Packed {
+                bytes: self.bytes(len)?,
+                compressed: false,
+            });
+        }
+        let packed_len = read_varint(&mut self.buffer).ok_or(StateError::Corrupted)?;
+        let bytes = self.bytes(packed_len)?;
+        if unpacked_len(bytes) != Some(len) {
+            return Err(StateError::Corrupted);
+        }
+        Ok(Packed {
+            bytes,
+            compressed: true,
+        })
+    }
+}
+
+/// Bytes read by [`StateReader::packed_slice`], of the expected
+/// length once decompressed.
+pub(crate) struct Packed<'a> {
+    bytes: &'a [u8],
+    compressed: bool,
+}
+
+impl Packed<'_> {
+    /// Decompress the bytes into `out`, which must have
+    /// the length checked by [`StateReader::packed_slice`].
+    pub fn unpack(&self, out: &mut [u8]) {
+        if self.compressed {
+            unpack(self.bytes, out).expect("the packed length was checked");
+        } else {
+            out.copy_from_slice(self.bytes);
+        }
+    }
+}
+
+/// Compress `bytes` by encoding the runs of zeros. The output is a
+/// sequence of a number of literal bytes, those bytes, and the
+/// number of zeros that follow them, the counts being varints.
+pub(crate) fn pack(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let literals = literal_len(rest);
+        write_varint(&mut out, literals);
+        out.extend_from_slice(&rest[..literals]);
+        rest = &rest[literals..];
+
+        let zeros = rest.iter().take_while(|byte| **byte == 0).count();
+        write_varint(&mut out, zeros);
+        rest = &rest[zeros..];
+    }
+    out
+}
+
+/// Decompress the output of [`pack`] into `out`. Return `None` if
+/// `packed` is malformed or doesn't decompress to exactly `out.len()` bytes.
+pub(crate) fn unpack(mut packed: &[u8], out: &mut [u8]) -> Option<()> {
+    let mut position = 0;
+    while !packed.is_empty() {
+        let literals = read_varint(&mut packed)?;
+        if literals > packed.len() || literals > out.len() - position {
+            return None;
+        }
+        out[position..position + literals].copy_from_slice(&packed[..literals]);
+        packed = &packed[literals..];
+        position += literals;
+
+        let zeros = read_varint(&mut packed)?;
+        if zeros > out.len() - position {
+            return None;
+        }
+        out[position..position + zeros].fill(0);
+        position += zeros;
+    }
+    (position == out.len()).then_some(())
+}
+
+// Length of the output of `pack` once decompressed, `None` if
+// `packed` is malformed.
+fn unpacked_len(mut packed: &[u8]) -> Option<usize> {
+    let mut len = 0usize;
+    while !packed.is_empty() {
+        let literals = read_varint(&mut packed)?;
+        packed = packed.get(literals..)?;
+        let zeros = read_varint(&mut packed)?;
+        len = len.checked_add(literals)?.checked_add(zeros)?;
+    }
+    Some(len)
+}
+
+// Number of bytes to copy as is, up to the next run of zeros
+// long enough to be worth encoding.
+fn literal_len(bytes: &[u8]) -> usize {
+    let mut len = 0;
+    while len < bytes.len() {
+        let zeros = bytes[len..]
+            .iter()
+            .take(4)
+            .take_while(|byte| **byte == 0)
+            .count();
+        if zeros == 4 || len + zeros == bytes.len() {
+            break;
+        }
+        len += zeros.max(1);
+    }
+    len
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Option<usize> {
+    let mut value = 0usize;
+    for shift in (0..usize::BITS).step_by(7) {
+        let (byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= ((byte & 0x7F) as usize).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
@@ -7,7 +7,7 @@ impl Timer {
         Timer { timer: 0 }
     }
 
-    pub fn reset(&mut self) -> () {
+    pub fn reset(&mut self) {
         self.timer = 0;
     }
 
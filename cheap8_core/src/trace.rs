@@ -0,0 +1,135 @@
+//! Execution traces, one line per executed instruction.
+//!
+//! Each line is the state of the CPU before the instruction executes:
+//!
+//! ```text
+//! 00000012 0204 6A02 va := 0x02               V 05 00 .. 00 I 0300 SP 1 DT 00 ST 00
+//! ```
+//!
+//! that is the cycle counter, the PC, the opcode, its disassembly,
+//! V0 to VF, I, SP and the timers, all in hexadecimal but the counter,
+//! so traces can be diffed between emulator versions.
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use crate::cpu::Cpu;
+use crate::disasm::{self, Syntax};
+use crate::instruction::{decode, Instruction};
+
+/// Writes the trace of the instructions executed by a [`Cpu`] to
+/// `W`, when given the CPU before each instruction is executed,
+/// see [`Cpu::run_frame_with`].
+pub struct Tracer<W: Write> {
+    writer: W,
+    range: RangeInclusive<u16>,
+    max_lines: Option<u64>,
+    syntax: Syntax,
+    lines: u64,
+    // First write error, after which nothing is written.
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Tracer<W> {
+    /// Create a tracer writing every instruction to `writer`, in
+    /// Octo syntax. Buffering it is up to the caller.
+    pub fn new(writer: W) -> Self {
+        Tracer {
+            writer,
+            range: 0..=u16::MAX,
+            max_lines: None,
+            syntax: Syntax::Octo,
+            lines: 0,
+            error: None,
+        }
+    }
+
+    /// Only trace the instructions at an address in `range`.
+    pub fn set_range(&mut self, range: RangeInclusive<u16>) {
+        self.range = range;
+    }
+
+    /// Stop tracing after `max_lines` lines, `None` for no limit.
+    pub fn set_max_lines(&mut self, max_lines: Option<u64>) {
+        self.max_lines = max_lines;
+    }
+
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    /// Number of lines written so far.
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// True iff the maximum number of lines was written,
+    /// or writing failed.
+    pub fn is_done(&self) -> bool {
+        self.error.is_some() || self.max_lines.is_some_and(|max| self.lines >= max)
+    }
+
+    /// Trace the instruction at the PC of `cpu`, which is about to
+    /// be executed, if it is in the address range.
+    pub fn trace(&mut self, cpu: &Cpu) {
+        if self.is_done() || !self.range.contains(&cpu.pc()) {
+            return;
+        }
+        let line = self.line(cpu);
+        match self.writer.write_all(line.as_bytes()) {
+            Ok(()) => self.lines += 1,
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    /// Flush the writer and return it, or the first write error.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn line(&self, cpu: &Cpu) -> String {
+        let pc = cpu.pc() as usize;
+        let memory = cpu.memory();
+        let word = |address: usize| {
+            memory
+                .get(address..address + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        };
+        let opcode = word(pc).unwrap_or(0);
+        let text = match decode(opcode) {
+            Ok(Instruction::SetIndexLong) => {
+                let long = word(pc + 2).unwrap_or(0);
+                match self.syntax {
+                    Syntax::Octo => format!("i := long 0x{:04X}", long),
+                    Syntax::Classic => format!("LD I, LONG #{:04X}", long),
+                }
+            }
+            Ok(instruction) => disasm::format(&instruction, self.syntax),
+            Err(_) => "???".to_string(),
+        };
+
+        let mut line = format!(
+            "{:08} {:04X} {:04X} {:<24} V",
+            cpu.cycles(),
+            pc,
+            opcode,
+            text
+        );
+        for register in cpu.registers() {
+            let _ = write!(line, " {:02X}", register);
+        }
+        let _ = writeln!(
+            line,
+            " I {:04X} SP {:X} DT {:02X} ST {:02X}",
+            cpu.index(),
+            cpu.stack().len(),
+            cpu.delay_timer(),
+            cpu.sound_timer()
+        );
+        line
+    }
+}
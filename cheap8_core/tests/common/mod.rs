@@ -0,0 +1,178 @@
+//! Helpers to build test ROMs and check what they draw.
+// Each test crate only uses some of them.
+#![allow(dead_code)]
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use cheap8_core::{Cpu, Instruction, Quirks, DEFAULT_LOAD_ADDRESS};
+
+/// Address of the check mark sprite, drawn by [`Assembler::check`] on success.
+pub const CHECK: u16 = 0xE00;
+/// Address of the cross sprite, drawn by [`Assembler::check`] on failure.
+pub const CROSS: u16 = 0xE05;
+/// Address of a free area of memory, for the ROMs to use.
+pub const SCRATCH: u16 = 0xF00;
+
+// Registers holding the position of the next mark.
+const MARK_X: u8 = 0xD;
+const MARK_Y: u8 = 0xE;
+
+/// Assembler writing instructions from the load address, and
+/// anything else at fixed addresses.
+pub struct Assembler {
+    memory: Vec<u8>,
+    // Address of the next instruction.
+    here: u16,
+    // Number of marks drawn so far.
+    marks: u8,
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        let mut assembler = Assembler {
+            memory: Vec::new(),
+            here: DEFAULT_LOAD_ADDRESS,
+            marks: 0,
+        };
+        assembler.data(CHECK, &[0x01, 0x02, 0x84, 0x48, 0x30]);
+        assembler.data(CROSS, &[0x88, 0x50, 0x20, 0x50, 0x88]);
+        assembler
+    }
+
+    /// Address of the next instruction.
+    pub fn here(&self) -> u16 {
+        self.here
+    }
+
+    pub fn op(&mut self, instruction: Instruction) -> &mut Self {
+        let here = self.here;
+        self.data(here, &instruction.encode().to_be_bytes());
+        self.here += 2;
+        self
+    }
+
+    pub fn ops(&mut self, instructions: &[Instruction]) -> &mut Self {
+        instructions.iter().for_each(|i| {
+            self.op(*i);
+        });
+        self
+    }
+
+    /// Write `instructions` at `address`, leaving the next
+    /// instruction where it is.
+    pub fn at(&mut self, address: u16, instructions: &[Instruction]) -> &mut Self {
+        let here = self.here;
+        self.here = address;
+        self.ops(instructions);
+        self.here = here;
+        self
+    }
+
+    /// Write `bytes` at `address`.
+    pub fn data(&mut self, address: u16, bytes: &[u8]) -> &mut Self {
+        let start = (address - DEFAULT_LOAD_ADDRESS) as usize;
+        if self.memory.len() < start + bytes.len() {
+            self.memory.resize(start + bytes.len(), 0);
+        }
+        self.memory[start..start + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    /// Draw a check mark if VX == NN and a cross otherwise, the
+    /// marks being laid out in rows of 8. It uses VD and VE, and
+    /// the drawing changes VF.
+    pub fn check(&mut self, x: u8, nn: u8) -> &mut Self {
+        let (column, row) = (self.marks % 8, self.marks / 8);
+        self.marks += 1;
+        self.ops(&[
+            Instruction::SetIndex { nnn: CROSS },
+            Instruction::SkipNeImm { x, nn },
+            Instruction::SetIndex { nnn: CHECK },
+            Instruction::SetImm {
+                x: MARK_X,
+                nn: column * 8,
+            },
+            Instruction::SetImm {
+                x: MARK_Y,
+                nn: row * 6,
+            },
+            Instruction::Draw {
+                x: MARK_X,
+                y: MARK_Y,
+                n: 5,
+            },
+        ])
+    }
+
+    /// Loop forever, the way test ROMs end.
+    pub fn halt(&mut self) -> &mut Self {
+        let here = self.here;
+        self.op(Instruction::Jump { nnn: here })
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.memory.clone()
+    }
+}
+
+/// Run `rom` for `frames` frames, with the keys returned by
+/// `keys` for each frame.
+pub fn run_with_keys<F>(rom: &[u8], quirks: Quirks, frames: usize, keys: F) -> Cpu
+where
+    F: Fn(usize) -> [bool; 16],
+{
+    let mut cpu = Cpu::with_seed(quirks, 0);
+    cpu.reset();
+    cpu.load_bytes(rom).expect("the ROM doesn't fit");
+    for frame in 0..frames {
+        cpu.run_frame(&keys(frame), 15)
+            .unwrap_or_else(|e| panic!("frame {}: {}", frame, e));
+    }
+    cpu
+}
+
+/// Run `rom` for `frames` frames without pressing any key.
+pub fn run(rom: &[u8], quirks: Quirks, frames: usize) -> Cpu {
+    run_with_keys(rom, quirks, frames, |_| [false; 16])
+}
+
+/// The screen of `cpu` as text, one line per row, with
+/// `.` for the background, `#` for the first plane, `+`
+/// for the second and `@` for both.
+pub fn screen(cpu: &Cpu) -> String {
+    let display = cpu.display();
+    let width = display.width();
+    let mut out = String::new();
+    for row in display.get()[..width * display.height()].chunks(width) {
+        out.extend(
+            row.iter()
+                .map(|pixel| ['.', '#', '+', '@'][*pixel as usize & 3]),
+        );
+        out.push('\n');
+    }
+    out
+}
+
+/// Compare the screen of `cpu` to the snapshot `name` in
+/// `tests/snapshots`. Set `UPDATE_SNAPSHOTS` to write the
+/// snapshot instead, after checking the screen is right.
+pub fn assert_snapshot(name: &str, cpu: &Cpu) {
+    let actual = screen(cpu);
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", name]
+        .iter()
+        .collect::<PathBuf>()
+        .with_extension("txt");
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, &actual).expect("couldn't write the snapshot");
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("couldn't read {}: {}", path.display(), e));
+    assert!(
+        actual == expected,
+        "the screen doesn't match {}:\n{}",
+        path.display(),
+        actual
+    );
+}
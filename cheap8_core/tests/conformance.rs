@@ -0,0 +1,430 @@
+//! Conformance tests, running test ROMs and comparing their screen
+//! to the snapshots in `tests/snapshots`.
+//!
+//! Except for the tiny IBM logo ROM, the ROMs are assembled here rather
+//! than vendored, and follow the community test suites: the opcode and
+//! flags ROMs draw a check mark for each passing test and a cross for
+//! each failing one, the quirks ROM draws a check mark for each quirk
+//! it detects. Run with `UPDATE_SNAPSHOTS=1` to regenerate the snapshots.
+mod common;
+
+use cheap8_core::Instruction::{self, *};
+use cheap8_core::Quirks;
+
+use common::{assert_snapshot, run, run_with_keys, Assembler, SCRATCH};
+
+// Sprites of one row, for the tests that need a plain one.
+const LINE: u16 = 0xE10;
+const DOT: u16 = 0xE11;
+
+fn set(x: u8, nn: u8) -> Instruction {
+    SetImm { x, nn }
+}
+
+// The IBM logo ROM, 6 sprites of 15 rows drawn side by side.
+const IBM_LOGO: [u8; 132] = [
+    0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08, 0xD0, 0x1F, 0x70, 0x09, 0xA2, 0x39, 0xD0, 0x1F,
+    0xA2, 0x48, 0x70, 0x08, 0xD0, 0x1F, 0x70, 0x04, 0xA2, 0x57, 0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x66,
+    0xD0, 0x1F, 0x70, 0x08, 0xA2, 0x75, 0xD0, 0x1F, 0x12, 0x28, 0xFF, 0x00, 0xFF, 0x00, 0x3C, 0x00,
+    0x3C, 0x00, 0x3C, 0x00, 0x3C, 0x00, 0xFF, 0x00, 0xFF, 0xFF, 0x00, 0xFF, 0x00, 0x38, 0x00, 0x3F,
+    0x00, 0x3F, 0x00, 0x38, 0x00, 0xFF, 0x00, 0xFF, 0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00,
+    0x80, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0xF8, 0x00, 0xFC, 0x00, 0x3E, 0x00, 0x3F, 0x00, 0x3B,
+    0x00, 0x39, 0x00, 0xF8, 0x00, 0xF8, 0x03, 0x00, 0x07, 0x00, 0x0F, 0x00, 0xBF, 0x00, 0xFB, 0x00,
+    0xF3, 0x00, 0xE3, 0x00, 0x43, 0xE0, 0x00, 0xE0, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80, 0x00, 0x80,
+    0x00, 0xE0, 0x00, 0xE0,
+];
+
+#[test]
+fn ibm_logo() {
+    for quirks in [Quirks::cosmac_vip(), Quirks::super_chip(), Quirks::modern()] {
+        assert_snapshot("ibm_logo", &run(&IBM_LOGO, quirks, 10));
+    }
+}
+
+#[test]
+fn font() {
+    // The 16 digits of the font on two rows, the last
+    // one wrapping around the right edge
+    let mut asm = Assembler::new();
+    asm.ops(&[Clear, set(0, 0), set(1, 1), set(2, 0)]);
+    for digit in 0..16 {
+        if digit == 8 {
+            asm.ops(&[set(1, 1), set(2, 8)]);
+        }
+        asm.ops(&[
+            set(0, digit),
+            Font { x: 0 },
+            Draw { x: 1, y: 2, n: 5 },
+            AddImm { x: 1, nn: 8 },
+        ]);
+    }
+    asm.ops(&[set(1, 61), set(2, 20), Draw { x: 1, y: 2, n: 5 }]);
+    asm.halt();
+
+    assert_snapshot("font", &run(&asm.build(), Quirks::modern(), 10));
+}
+
+#[test]
+fn opcodes() {
+    let mut asm = Assembler::new();
+    asm.op(Clear);
+
+    // 6XNN, 7XNN without touching VF, 8XY0
+    asm.ops(&[set(0xF, 0x55), set(1, 0x42)]).check(1, 0x42);
+    asm.ops(&[
+        set(0xF, 0x55),
+        AddImm { x: 1, nn: 0xC0 },
+        Set { x: 3, y: 0xF },
+    ])
+    .check(1, 0x02)
+    .check(3, 0x55);
+    asm.op(Set { x: 2, y: 1 }).check(2, 0x02);
+
+    // 8XY1, 8XY2, 8XY3
+    asm.ops(&[set(1, 0xF0), set(2, 0x0F), Or { x: 1, y: 2 }])
+        .check(1, 0xFF);
+    asm.ops(&[set(1, 0xF3), set(2, 0x3C), And { x: 1, y: 2 }])
+        .check(1, 0x30);
+    asm.ops(&[set(1, 0xF3), set(2, 0x3C), Xor { x: 1, y: 2 }])
+        .check(1, 0xCF);
+
+    // 8XY4, 8XY5, 8XY7
+    asm.ops(&[set(1, 0xF0), set(2, 0x20), Add { x: 1, y: 2 }])
+        .check(1, 0x10);
+    asm.ops(&[set(1, 0x30), set(2, 0x10), Sub { x: 1, y: 2 }])
+        .check(1, 0x20);
+    asm.ops(&[set(1, 0x10), set(2, 0x30), Sub { x: 1, y: 2 }])
+        .check(1, 0xE0);
+    asm.ops(&[set(1, 0x10), set(2, 0x30), SubReverse { x: 1, y: 2 }])
+        .check(1, 0x20);
+
+    // 8XY6 and 8XYE, shifting a register by itself
+    // to behave the same whatever the quirks
+    asm.ops(&[set(1, 0x81), ShiftRight { x: 1, y: 1 }])
+        .check(1, 0x40);
+    asm.ops(&[set(1, 0x81), ShiftLeft { x: 1, y: 1 }])
+        .check(1, 0x02);
+
+    // 3XNN, 4XNN, 5XY0, 9XY0, skipping when the test holds
+    let skip = |asm: &mut Assembler, test: Instruction, skipped: bool| {
+        asm.ops(&[set(3, 0), test, set(3, 1)])
+            .check(3, if skipped { 0 } else { 1 });
+    };
+    asm.ops(&[set(1, 0x12), set(2, 0x12), set(4, 0x34)]);
+    skip(&mut asm, SkipEqImm { x: 1, nn: 0x12 }, true);
+    skip(&mut asm, SkipEqImm { x: 1, nn: 0x13 }, false);
+    skip(&mut asm, SkipNeImm { x: 1, nn: 0x13 }, true);
+    skip(&mut asm, SkipNeImm { x: 1, nn: 0x12 }, false);
+    skip(&mut asm, SkipEq { x: 1, y: 2 }, true);
+    skip(&mut asm, SkipEq { x: 1, y: 4 }, false);
+    skip(&mut asm, SkipNe { x: 1, y: 4 }, true);
+    skip(&mut asm, SkipNe { x: 1, y: 2 }, false);
+
+    // 2NNN and 00EE
+    asm.at(0xC00, &[set(4, 0x99), Return]);
+    asm.ops(&[set(4, 0), Call { nnn: 0xC00 }]).check(4, 0x99);
+
+    // BNNN, with V0 == VC so that the jump quirk doesn't matter
+    let back = asm.here() + 6;
+    asm.at(0xC10, &[set(5, 0xBA), Jump { nnn: back }]);
+    asm.at(0xC14, &[set(5, 0x01), Jump { nnn: back }]);
+    asm.ops(&[set(0, 4), set(0xC, 4), JumpOffset { x: 0xC, nnn: 0xC10 }])
+        .check(5, 0x01);
+
+    // ANNN, FX1E, FX55 and FX65
+    asm.ops(&[
+        SetIndex { nnn: SCRATCH },
+        set(1, 0x10),
+        AddIndex { x: 1 },
+        set(0, 0xA1),
+        set(1, 0xB2),
+        Store { x: 1 },
+        set(0, 0),
+        set(1, 0),
+        SetIndex {
+            nnn: SCRATCH + 0x10,
+        },
+        Load { x: 1 },
+    ])
+    .check(0, 0xA1)
+    .check(1, 0xB2);
+
+    // FX33
+    asm.ops(&[
+        set(1, 137),
+        SetIndex { nnn: SCRATCH },
+        Bcd { x: 1 },
+        Load { x: 2 },
+    ])
+    .check(0, 1)
+    .check(1, 3)
+    .check(2, 7);
+
+    // FX29, reading the first row of the B digit
+    asm.ops(&[set(1, 0xB), Font { x: 1 }, Load { x: 0 }])
+        .check(0, 0xE0);
+
+    // FX15 and FX07
+    asm.ops(&[set(1, 0x20), SetDelay { x: 1 }, GetDelay { x: 2 }])
+        .check(2, 0x20);
+
+    // CXNN with a null mask
+    asm.op(Random { x: 6, nn: 0 }).check(6, 0);
+
+    // DXYN, collision when drawing twice
+    asm.ops(&[
+        SetIndex { nnn: LINE },
+        set(1, 32),
+        set(2, 30),
+        Draw { x: 1, y: 2, n: 1 },
+        Set { x: 3, y: 0xF },
+        Draw { x: 1, y: 2, n: 1 },
+        Set { x: 4, y: 0xF },
+    ])
+    .check(3, 0)
+    .check(4, 1);
+
+    asm.data(LINE, &[0xFF]);
+    asm.halt();
+
+    for quirks in [Quirks::cosmac_vip(), Quirks::chip48(), Quirks::modern()] {
+        assert_snapshot("opcodes", &run(&asm.build(), quirks, 120));
+    }
+}
+
+#[test]
+fn flags() {
+    let mut asm = Assembler::new();
+    asm.op(Clear);
+
+    // Each operation with and without carry, checking
+    // the result and VF, copied to V3
+    let flag = |asm: &mut Assembler, a: u8, b: u8, op: Instruction, result: u8, vf: u8| {
+        asm.ops(&[set(1, a), set(2, b), op, Set { x: 3, y: 0xF }])
+            .check(1, result)
+            .check(3, vf);
+    };
+    flag(&mut asm, 0x10, 0x20, Add { x: 1, y: 2 }, 0x30, 0);
+    flag(&mut asm, 0xF0, 0x20, Add { x: 1, y: 2 }, 0x10, 1);
+    flag(&mut asm, 0x30, 0x10, Sub { x: 1, y: 2 }, 0x20, 1);
+    flag(&mut asm, 0x10, 0x30, Sub { x: 1, y: 2 }, 0xE0, 0);
+    flag(&mut asm, 0x10, 0x10, Sub { x: 1, y: 2 }, 0x00, 1);
+    flag(&mut asm, 0x10, 0x30, SubReverse { x: 1, y: 2 }, 0x20, 1);
+    flag(&mut asm, 0x30, 0x10, SubReverse { x: 1, y: 2 }, 0xE0, 0);
+    flag(&mut asm, 0x81, 0x81, ShiftRight { x: 1, y: 1 }, 0x40, 1);
+    flag(&mut asm, 0x80, 0x80, ShiftRight { x: 1, y: 1 }, 0x40, 0);
+    flag(&mut asm, 0x81, 0x81, ShiftLeft { x: 1, y: 1 }, 0x02, 1);
+    flag(&mut asm, 0x41, 0x41, ShiftLeft { x: 1, y: 1 }, 0x82, 0);
+
+    // VF as the destination, where the flag wins over the result
+    let vf = |asm: &mut Assembler, a: u8, b: u8, op: Instruction, vf: u8| {
+        asm.ops(&[set(0xF, a), set(1, b), op]).check(0xF, vf);
+    };
+    vf(&mut asm, 0xF0, 0x20, Add { x: 0xF, y: 1 }, 1);
+    vf(&mut asm, 0x10, 0x20, Add { x: 0xF, y: 1 }, 0);
+    vf(&mut asm, 0x30, 0x10, Sub { x: 0xF, y: 1 }, 1);
+    vf(&mut asm, 0x10, 0x30, Sub { x: 0xF, y: 1 }, 0);
+    vf(&mut asm, 0x10, 0x30, SubReverse { x: 0xF, y: 1 }, 1);
+    vf(&mut asm, 0x30, 0x10, SubReverse { x: 0xF, y: 1 }, 0);
+    vf(&mut asm, 0x81, 0, ShiftRight { x: 0xF, y: 0xF }, 1);
+    vf(&mut asm, 0x80, 0, ShiftRight { x: 0xF, y: 0xF }, 0);
+    vf(&mut asm, 0x81, 0, ShiftLeft { x: 0xF, y: 0xF }, 1);
+    vf(&mut asm, 0x41, 0, ShiftLeft { x: 0xF, y: 0xF }, 0);
+
+    // VF as an operand, read before being overwritten
+    asm.ops(&[set(1, 0x10), set(0xF, 0x20), Add { x: 1, y: 0xF }])
+        .check(1, 0x30);
+
+    asm.halt();
+
+    assert_snapshot("flags", &run(&asm.build(), Quirks::modern(), 120));
+}
+
+// Draw a check mark for each quirk detected, in the order
+// of the fields of `Quirks`.
+fn quirks_rom() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    asm.op(Clear);
+
+    // Clipping: draw a line across the right edge, and
+    // collide with it at the left edge if it wrapped
+    asm.ops(&[
+        SetIndex { nnn: LINE },
+        set(1, 60),
+        set(2, 0),
+        Draw { x: 1, y: 2, n: 1 },
+        SetIndex { nnn: DOT },
+        set(1, 0),
+        Draw { x: 1, y: 2, n: 1 },
+        Set { x: 8, y: 0xF },
+        Clear,
+    ]);
+
+    // Display wait: 10 sprites take 9 frames if each waits for
+    // the next one, less than one otherwise
+    asm.ops(&[set(1, 10), SetDelay { x: 1 }, SetIndex { nnn: DOT }]);
+    for _ in 0..10 {
+        asm.op(Draw { x: 0, y: 0, n: 1 });
+    }
+    asm.ops(&[GetDelay { x: 9 }, Clear]);
+
+    // Shifting VY
+    asm.ops(&[
+        set(1, 1),
+        set(2, 4),
+        ShiftRight { x: 1, y: 2 },
+        Set { x: 5, y: 1 },
+    ]);
+
+    // Incrementing I: the second store overwrites the first one if not
+    asm.ops(&[
+        SetIndex { nnn: SCRATCH },
+        set(0, 0x11),
+        Store { x: 0 },
+        set(0, 0x22),
+        Store { x: 0 },
+        SetIndex { nnn: SCRATCH },
+        Load { x: 0 },
+        Set { x: 6, y: 0 },
+    ]);
+
+    // Jumping to NNN + VX, with X = C
+    let back = asm.here() + 6;
+    asm.at(0xC10, &[set(7, 0), Jump { nnn: back }]);
+    asm.at(0xC14, &[set(7, 1), Jump { nnn: back }]);
+    asm.ops(&[set(0, 0), set(0xC, 4), JumpOffset { x: 0xC, nnn: 0xC10 }]);
+
+    // Resetting VF
+    asm.ops(&[
+        set(0xF, 5),
+        set(1, 0),
+        Or { x: 1, y: 1 },
+        Set { x: 4, y: 0xF },
+    ]);
+
+    asm.check(5, 2)
+        .check(6, 0x11)
+        .check(7, 1)
+        .check(4, 0)
+        .check(8, 0)
+        .check(9, 0)
+        .halt();
+    asm.data(LINE, &[0xFF, 0x80]);
+    asm.build()
+}
+
+#[test]
+fn quirks_cosmac_vip() {
+    assert_snapshot("quirks_vip", &run(&quirks_rom(), Quirks::cosmac_vip(), 120));
+}
+
+#[test]
+fn quirks_chip48() {
+    assert_snapshot("quirks_chip48", &run(&quirks_rom(), Quirks::chip48(), 120));
+}
+
+#[test]
+fn quirks_super_chip() {
+    assert_snapshot(
+        "quirks_chip48",
+        &run(&quirks_rom(), Quirks::super_chip(), 120),
+    );
+}
+
+#[test]
+fn quirks_modern() {
+    assert_snapshot("quirks_modern", &run(&quirks_rom(), Quirks::modern(), 120));
+}
+
+#[test]
+fn keypad() {
+    let mut asm = Assembler::new();
+    asm.op(Clear);
+
+    // FX0A, then draw the key
+    asm.ops(&[
+        WaitKey { x: 1 },
+        Font { x: 1 },
+        set(2, 40),
+        set(3, 20),
+        Draw { x: 2, y: 3, n: 5 },
+    ]);
+    // EX9E while the key is held
+    asm.ops(&[set(3, 0), SkipKey { x: 1 }, set(3, 1)])
+        .check(3, 0);
+    // EXA1 once it is released
+    let wait = asm.here();
+    asm.ops(&[SkipNotKey { x: 1 }, Jump { nnn: wait }]);
+    asm.ops(&[set(3, 0), SkipNotKey { x: 1 }, set(3, 1)])
+        .check(3, 0);
+    asm.halt();
+
+    let cpu = run_with_keys(&asm.build(), Quirks::modern(), 60, |frame| {
+        let mut keys = [false; 16];
+        keys[0xA] = (10..20).contains(&frame);
+        keys
+    });
+    assert_snapshot("keypad", &cpu);
+}
+
+#[test]
+fn super_chip_display() {
+    // Big digits in high resolution, scrolled down and right
+    let mut asm = Assembler::new();
+    asm.ops(&[HighRes, Clear, set(1, 0), set(2, 0)]);
+    for digit in [0xC, 0x8] {
+        asm.ops(&[
+            set(0, digit),
+            BigFont { x: 0 },
+            Draw { x: 1, y: 2, n: 10 },
+            AddImm { x: 1, nn: 10 },
+        ]);
+    }
+    asm.ops(&[
+        SetIndex { nnn: 0xE20 },
+        set(1, 120),
+        set(2, 56),
+        Draw { x: 1, y: 2, n: 0 },
+        ScrollDown { n: 4 },
+        ScrollRight,
+    ]);
+    asm.data(0xE20, &[0xFF; 32]);
+    asm.halt();
+
+    assert_snapshot(
+        "super_chip_display",
+        &run(&asm.build(), Quirks::super_chip(), 10),
+    );
+}
+
+#[test]
+fn xo_chip_planes() {
+    // A square on each plane, overlapping, then scrolled up
+    let mut asm = Assembler::new();
+    asm.ops(&[
+        Clear,
+        SelectPlanes { x: 3 },
+        Clear,
+        SelectPlanes { x: 1 },
+        SetIndex { nnn: 0xE20 },
+        set(1, 8),
+        set(2, 8),
+        Draw { x: 1, y: 2, n: 8 },
+        SelectPlanes { x: 2 },
+        set(1, 12),
+        set(2, 12),
+        Draw { x: 1, y: 2, n: 8 },
+        SelectPlanes { x: 3 },
+        SetIndex { nnn: 0xE28 },
+        set(1, 40),
+        set(2, 8),
+        Draw { x: 1, y: 2, n: 4 },
+        ScrollUp { n: 2 },
+    ]);
+    asm.data(0xE20, &[0xFF; 8]);
+    // The rows of the first plane, then those of the second one
+    asm.data(0xE28, &[0xF0, 0xFF, 0x0F, 0xFF, 0xF0, 0x0F, 0x0F, 0xF0]);
+    asm.halt();
+
+    assert_snapshot("xo_chip_planes", &run(&asm.build(), Quirks::modern(), 10));
+}
@@ -0,0 +1,94 @@
+use cheap8_core::disasm::{listing, Line};
+use cheap8_core::Instruction;
+
+#[test]
+fn code_is_split_from_data() {
+    let rom = [
+        0x12, 0x06, // jump 0x206
+        0xAA, 0xBB, 0xCC, 0xDD, // sprite
+        0x00, 0xE0, // clear
+        0x12, 0x08, // jump 0x208
+    ];
+    let lines = listing(&rom, 0x200);
+    assert_eq!(
+        lines,
+        [
+            Line::Code {
+                address: 0x200,
+                opcode: 0x1206,
+                instruction: Instruction::Jump { nnn: 0x206 },
+                long: None,
+            },
+            Line::Data {
+                address: 0x202,
+                byte: 0xAA,
+            },
+            Line::Data {
+                address: 0x203,
+                byte: 0xBB,
+            },
+            Line::Data {
+                address: 0x204,
+                byte: 0xCC,
+            },
+            Line::Data {
+                address: 0x205,
+                byte: 0xDD,
+            },
+            Line::Code {
+                address: 0x206,
+                opcode: 0x00E0,
+                instruction: Instruction::Clear,
+                long: None,
+            },
+            Line::Code {
+                address: 0x208,
+                opcode: 0x1208,
+                instruction: Instruction::Jump { nnn: 0x208 },
+                long: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn skipped_instructions_are_code() {
+    // Skip a call to a subroutine, then loop, the last byte being data
+    let rom = [0x30, 0x01, 0x22, 0x06, 0x12, 0x04, 0x00, 0xEE, 0xFF];
+    let lines = listing(&rom, 0x200);
+    let code: Vec<bool> = lines
+        .iter()
+        .map(|line| matches!(line, Line::Code { .. }))
+        .collect();
+    assert_eq!(code, [true, true, true, true, false]);
+}
+
+#[test]
+fn listing_stops_at_the_end_of_memory() {
+    // The last two bytes don't fit in memory
+    let rom = [0x12, 0x00, 0x00, 0x00, 0xAB, 0xCD];
+    let lines = listing(&rom, 0xFFFC);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        Line::Code {
+            address: 0xFFFC,
+            opcode: 0x1200,
+            instruction: Instruction::Jump { nnn: 0x200 },
+            long: None,
+        }
+    );
+    assert_eq!(
+        lines[1..],
+        [
+            Line::Data {
+                address: 0xFFFE,
+                byte: 0x00,
+            },
+            Line::Data {
+                address: 0xFFFF,
+                byte: 0x00,
+            },
+        ]
+    );
+}
@@ -0,0 +1,67 @@
+use cheap8_core::{Cpu, CpuError, Quirks};
+
+// Cycle a CPU running `rom` until it fails.
+fn error(rom: &[u8]) -> CpuError {
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 0);
+    cpu.reset();
+    cpu.load_bytes(rom).unwrap();
+    for _ in 0..100 {
+        if let Err(e) = cpu.cycle(&[false; 16]) {
+            return e;
+        }
+    }
+    panic!("the ROM didn't fail");
+}
+
+#[test]
+fn unknown_opcode() {
+    let rom = [
+        0x60, 0x01, // v0 := 0x01
+        0xE0, 0x00, // no such instruction
+    ];
+    let e = error(&rom);
+    assert_eq!(
+        e,
+        CpuError::UnknownOpcode {
+            address: 0x202,
+            opcode: 0xE000
+        }
+    );
+    assert_eq!(e.to_string(), "unknown opcode 0xe000 at 0x202");
+}
+
+#[test]
+fn stack_overflow() {
+    // Calls itself until the 16 levels of the stack are full
+    let rom = [0x22, 0x00];
+    assert_eq!(error(&rom), CpuError::StackOverflow { address: 0x200 });
+}
+
+#[test]
+fn stack_underflow() {
+    let rom = [
+        0x00, 0xE0, // clear
+        0x00, 0xEE, // return
+    ];
+    assert_eq!(error(&rom), CpuError::StackUnderflow { address: 0x202 });
+}
+
+#[test]
+fn memory_out_of_range() {
+    let rom = [
+        0xF0, 0x00, 0xFF, 0xF8, // i := long 0xFFF8
+        0xF0, 0x02, // audio, reading 16 bytes from i
+    ];
+    let e = error(&rom);
+    assert_eq!(
+        e,
+        CpuError::MemoryOutOfRange {
+            address: 0x204,
+            access: 0x10000
+        }
+    );
+    assert_eq!(
+        e.to_string(),
+        "out of range memory access to 0x10000 at 0x204"
+    );
+}
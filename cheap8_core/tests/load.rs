@@ -0,0 +1,43 @@
+use cheap8_core::{Cpu, LoadError, Quirks, DEFAULT_LOAD_ADDRESS};
+
+fn cpu() -> Cpu {
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 0);
+    cpu.reset();
+    cpu
+}
+
+#[test]
+fn rom_too_large() {
+    let mut cpu = cpu();
+    let max = 0x10000 - DEFAULT_LOAD_ADDRESS as usize;
+    cpu.load_bytes(&vec![0xAA; max]).unwrap();
+
+    cpu.reset();
+    match cpu.load_bytes(&vec![0xAA; max + 1]) {
+        Err(LoadError::RomTooLarge { size, max: limit }) => {
+            assert_eq!(size, max + 1);
+            assert_eq!(limit, max);
+        }
+        other => panic!("expected RomTooLarge, got {:?}", other),
+    }
+    // Memory is left untouched
+    assert!(cpu.memory()[DEFAULT_LOAD_ADDRESS as usize..]
+        .iter()
+        .all(|byte| *byte == 0));
+}
+
+#[test]
+fn load_address() {
+    let mut cpu = cpu();
+    cpu.set_load_address(0x600);
+    cpu.reset();
+    cpu.load_bytes(&[0x60, 0x2A, 0x16, 0x02]).unwrap();
+    assert_eq!(cpu.load_address(), 0x600);
+    assert_eq!(cpu.pc(), 0x600);
+    assert_eq!(cpu.memory()[0x600..0x604], [0x60, 0x2A, 0x16, 0x02]);
+    assert_eq!(cpu.memory()[0x200..0x204], [0; 4]);
+
+    cpu.run_frame(&[false; 16], 10).unwrap();
+    assert_eq!(cpu.registers()[0], 0x2A);
+    assert_eq!(cpu.pc(), 0x602);
+}
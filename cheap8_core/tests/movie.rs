@@ -0,0 +1,83 @@
+mod common;
+
+use cheap8_core::movie::Movie;
+use cheap8_core::Instruction::*;
+use cheap8_core::{Cpu, MovieError, Quirks};
+
+use common::Assembler;
+
+// ROM whose state depends on the random generator and on when key
+// 5 is pressed: V2 sums random bytes while it is, and is drawn.
+fn rom() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    asm.op(SetImm { x: 3, nn: 5 });
+    let start = asm.here();
+    asm.ops(&[
+        Random { x: 1, nn: 0xFF },
+        SkipNotKey { x: 3 },
+        Add { x: 2, y: 1 },
+        Clear,
+        Font { x: 2 },
+        Draw { x: 1, y: 1, n: 5 },
+        Jump { nnn: start },
+    ]);
+    asm.build()
+}
+
+fn keys(frame: u64) -> [bool; 16] {
+    let mut keys = [false; 16];
+    keys[5] = frame % 7 < 3;
+    keys[frame as usize % 16] = true;
+    keys
+}
+
+#[test]
+fn replay_is_exact() {
+    let mut cpu = Cpu::with_seed(Quirks::chip48(), 1234);
+    cpu.set_load_address(0x200);
+    cpu.reset();
+    cpu.load_bytes(&rom()).unwrap();
+    let mut movie = Movie::new(&cpu, 11);
+    for _ in 0..100 {
+        let frame = cpu.frames();
+        movie.record(frame, &keys(frame));
+        cpu.run_frame(&keys(frame), 11).unwrap();
+    }
+
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+    assert_eq!(movie.len(), 100);
+    let mut replay = movie.cpu();
+    replay.load_bytes(&rom()).unwrap();
+    movie.check_rom(&replay).unwrap();
+    while let Some(keys) = movie.keys(replay.frames()) {
+        replay.run_frame(&keys, movie.ipf).unwrap();
+    }
+    assert!(replay.save_state() == cpu.save_state());
+}
+
+#[test]
+fn recording_again_replaces_the_end() {
+    let cpu = Cpu::with_seed(Quirks::modern(), 0);
+    let mut movie = Movie::new(&cpu, 15);
+    for frame in 0..10 {
+        movie.record(frame, &keys(frame));
+    }
+    movie.record(4, &[true; 16]);
+    assert_eq!(movie.len(), 5);
+    assert_eq!(movie.keys(3), Some(keys(3)));
+    assert_eq!(movie.keys(4), Some([true; 16]));
+    assert_eq!(movie.keys(5), None);
+}
+
+#[test]
+fn other_rom_is_rejected() {
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 0);
+    cpu.load_bytes(&rom()).unwrap();
+    let movie = Movie::new(&cpu, 15);
+    cpu.load_bytes(&[0x12, 0x00]).unwrap();
+    assert!(matches!(
+        movie.check_rom(&cpu),
+        Err(MovieError::RomMismatch { .. })
+    ));
+    assert_eq!(Movie::from_bytes(b"C8ST"), Err(MovieError::NotAMovie));
+}
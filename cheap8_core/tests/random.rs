@@ -0,0 +1,36 @@
+mod common;
+
+use cheap8_core::Instruction::*;
+use cheap8_core::{Cpu, Quirks};
+
+use common::Assembler;
+
+// Registers after running a ROM filling them with CXNN in loop.
+fn registers(seed: u64) -> [u8; 16] {
+    let mut asm = Assembler::new();
+    let start = asm.here();
+    for x in 0..16 {
+        asm.op(Random { x, nn: 0xFF });
+    }
+    asm.op(Jump { nnn: start });
+    let mut cpu = Cpu::with_seed(Quirks::modern(), seed);
+    cpu.reset();
+    cpu.load_bytes(&asm.build()).unwrap();
+    for _ in 0..10 {
+        cpu.run_frame(&[false; 16], 15).unwrap();
+    }
+    assert_eq!(cpu.seed(), seed);
+    *cpu.registers()
+}
+
+#[test]
+fn same_seed_same_numbers() {
+    assert_eq!(registers(42), registers(42));
+    assert_eq!(registers(0), registers(0));
+}
+
+#[test]
+fn other_seed_other_numbers() {
+    assert_ne!(registers(42), registers(43));
+    assert_ne!(registers(0), registers(1));
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use cheap8_core::rewind::Rewind;
+use cheap8_core::Instruction::*;
+use cheap8_core::{Cpu, Quirks};
+
+use common::Assembler;
+
+// Cpu running a ROM that draws random digits at random places,
+// so that its state changes every frame.
+fn busy_cpu() -> Cpu {
+    let mut asm = Assembler::new();
+    let start = asm.here();
+    asm.ops(&[
+        Random { x: 0, nn: 0xF },
+        Random { x: 1, nn: 0x3F },
+        Random { x: 2, nn: 0x1F },
+        Font { x: 0 },
+        Draw { x: 1, y: 2, n: 5 },
+        SetIndex { nnn: 0xF00 },
+        Store { x: 2 },
+        Jump { nnn: start },
+    ]);
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 7);
+    cpu.reset();
+    cpu.load_bytes(&asm.build()).unwrap();
+    cpu
+}
+
+#[test]
+fn step_back_restores_each_frame() {
+    let mut cpu = busy_cpu();
+    let mut rewind = Rewind::new(100);
+    let mut states = Vec::new();
+    for _ in 0..50 {
+        cpu.run_frame(&[false; 16], 15).unwrap();
+        rewind.push(&cpu);
+        states.push(cpu.save_state());
+    }
+    assert_eq!(rewind.len(), 49);
+
+    for state in states.iter().rev().skip(1) {
+        assert_eq!(rewind.step_back(&mut cpu), Ok(true));
+        assert!(cpu.save_state() == *state);
+    }
+    assert_eq!(rewind.step_back(&mut cpu), Ok(false));
+    assert!(cpu.save_state() == states[0]);
+
+    // Running again from there gives the same frames
+    for state in &states[1..] {
+        cpu.run_frame(&[false; 16], 15).unwrap();
+        assert!(cpu.save_state() == *state);
+    }
+}
+
+#[test]
+fn oldest_states_are_dropped() {
+    let mut cpu = busy_cpu();
+    let mut rewind = Rewind::new(10);
+    let mut states = Vec::new();
+    for _ in 0..30 {
+        cpu.run_frame(&[false; 16], 15).unwrap();
+        rewind.push(&cpu);
+        states.push(cpu.save_state());
+    }
+    assert_eq!(rewind.len(), 10);
+
+    while rewind.step_back(&mut cpu).unwrap() {}
+    assert!(cpu.save_state() == states[19]);
+}
+
+#[test]
+fn no_state_is_kept_without_capacity() {
+    let mut cpu = busy_cpu();
+    let mut rewind = Rewind::new(0);
+    let state = cpu.save_state();
+    for _ in 0..3 {
+        rewind.push(&cpu);
+        cpu.run_frame(&[false; 16], 15).unwrap();
+    }
+    assert!(rewind.is_empty());
+    assert_eq!(rewind.step_back(&mut cpu), Ok(false));
+    assert!(cpu.save_state() != state);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use cheap8_core::Instruction::*;
+use cheap8_core::{rom_hash, Cpu, Quirks, StateError};
+
+use common::Assembler;
+
+// ROM drawing random digits at random places.
+fn rom() -> Vec<u8> {
+    let mut asm = Assembler::new();
+    let start = asm.here();
+    asm.ops(&[
+        Random { x: 0, nn: 0xF },
+        Random { x: 1, nn: 0x3F },
+        Random { x: 2, nn: 0x1F },
+        Font { x: 0 },
+        Draw { x: 1, y: 2, n: 5 },
+        Jump { nnn: start },
+    ]);
+    asm.build()
+}
+
+fn cpu(rom: &[u8]) -> Cpu {
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 3);
+    cpu.reset();
+    cpu.load_bytes(rom).unwrap();
+    cpu
+}
+
+fn run(cpu: &mut Cpu, frames: usize) {
+    for _ in 0..frames {
+        cpu.run_frame(&[false; 16], 15).unwrap();
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut cpu = cpu(&rom());
+    run(&mut cpu, 10);
+    let state = cpu.save_state();
+    run(&mut cpu, 10);
+    let later = cpu.save_state();
+
+    cpu.load_state(&state).unwrap();
+    assert!(cpu.save_state() == state);
+    assert_eq!(cpu.frames(), 10);
+    // The random generator is restored too
+    run(&mut cpu, 10);
+    assert!(cpu.save_state() == later);
+}
+
+#[test]
+fn invalid_states() {
+    let mut cpu = cpu(&rom());
+    run(&mut cpu, 10);
+    let state = cpu.save_state();
+    run(&mut cpu, 1);
+    let current = cpu.save_state();
+
+    assert_eq!(cpu.load_state(b"not a state"), Err(StateError::NotAState));
+
+    let mut version = state.clone();
+    version[4] = 99;
+    assert_eq!(
+        cpu.load_state(&version),
+        Err(StateError::UnsupportedVersion { version: 99 })
+    );
+
+    let truncated = &state[..state.len() - 1];
+    assert_eq!(cpu.load_state(truncated), Err(StateError::Corrupted));
+
+    // The CPU is left untouched on error
+    assert!(cpu.save_state() == current);
+}
+
+#[test]
+fn another_rom() {
+    let state = cpu(&rom()).save_state();
+    let other = [0x12, 0x00];
+    let mut cpu = cpu(&other);
+    assert_eq!(
+        cpu.load_state(&state),
+        Err(StateError::RomMismatch {
+            expected: rom_hash(&other),
+            found: rom_hash(&rom()),
+        })
+    );
+}
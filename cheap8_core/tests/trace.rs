@@ -0,0 +1,111 @@
+mod common;
+
+use std::io::{self, Write};
+
+use cheap8_core::trace::Tracer;
+use cheap8_core::Instruction::*;
+use cheap8_core::{Cpu, Quirks};
+
+use common::Assembler;
+
+// Cpu running a ROM that sets a few registers, the delay
+// timer, and calls a subroutine before looping.
+fn cpu() -> Cpu {
+    let mut asm = Assembler::new();
+    asm.ops(&[
+        SetImm { x: 0xA, nn: 0x02 },
+        SetIndex { nnn: 0x300 },
+        SetImm { x: 3, nn: 0x05 },
+        SetDelay { x: 3 },
+        Call { nnn: 0x20C },
+    ]);
+    asm.halt();
+    asm.op(Return);
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 0);
+    cpu.reset();
+    cpu.load_bytes(&asm.build()).unwrap();
+    cpu
+}
+
+// Run `frames` frames of `cpu` with `tracer`.
+fn trace<W: Write>(cpu: &mut Cpu, tracer: &mut Tracer<W>, frames: usize) {
+    for _ in 0..frames {
+        cpu.run_frame_with(&[false; 16], 8, |cpu| tracer.trace(cpu))
+            .unwrap();
+    }
+}
+
+#[test]
+fn one_line_per_instruction() {
+    let mut cpu = cpu();
+    let mut tracer = Tracer::new(Vec::new());
+    trace(&mut cpu, &mut tracer, 1);
+    assert_eq!(tracer.lines(), 8);
+    let trace = String::from_utf8(tracer.finish().unwrap()).unwrap();
+    let lines: Vec<_> = trace.lines().collect();
+    assert_eq!(lines.len(), 8);
+    // The state before the return, in the subroutine
+    assert_eq!(
+        lines[5],
+        "00000005 020C 00EE return                   \
+         V 00 00 00 05 00 00 00 00 00 00 02 00 00 00 00 00 I 0300 SP 1 DT 05 ST 00"
+    );
+}
+
+// Addresses of the instructions traced.
+fn addresses(trace: &[u8]) -> Vec<&str> {
+    std::str::from_utf8(trace)
+        .unwrap()
+        .lines()
+        .map(|line| &line[9..13])
+        .collect()
+}
+
+#[test]
+fn only_the_range_is_traced() {
+    let mut cpu = cpu();
+    let mut tracer = Tracer::new(Vec::new());
+    tracer.set_range(0x204..=0x208);
+    trace(&mut cpu, &mut tracer, 1);
+    let trace = tracer.finish().unwrap();
+    assert_eq!(addresses(&trace), ["0204", "0206", "0208"]);
+}
+
+#[test]
+fn stops_after_max_lines() {
+    let mut cpu = cpu();
+    let mut tracer = Tracer::new(Vec::new());
+    tracer.set_max_lines(Some(3));
+    assert!(!tracer.is_done());
+    trace(&mut cpu, &mut tracer, 2);
+    assert!(tracer.is_done());
+    assert_eq!(tracer.lines(), 3);
+    let trace = tracer.finish().unwrap();
+    assert_eq!(addresses(&trace), ["0200", "0202", "0204"]);
+}
+
+// Writer failing on every write, with the number of the write.
+#[derive(Default)]
+struct Failing(usize);
+
+impl Write for Failing {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        self.0 += 1;
+        Err(io::Error::other(format!("write {}", self.0)))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn first_write_error_is_kept() {
+    let mut cpu = cpu();
+    let mut tracer = Tracer::new(Failing::default());
+    trace(&mut cpu, &mut tracer, 2);
+    assert!(tracer.is_done());
+    assert_eq!(tracer.lines(), 0);
+    let error = tracer.finish().err().unwrap();
+    assert_eq!(error.to_string(), "write 1");
+}
@@ -0,0 +1,202 @@
+//! Command line of the frontends: the arguments they have in common,
+//! and the [`Runner`] they set up from them before adding their drivers.
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use cheap8_core::disasm::{self, Syntax};
+use cheap8_core::movie::Movie;
+use cheap8_core::trace::Tracer;
+use cheap8_core::{rom_hash, Cpu, Quirks};
+
+use crate::parse::{parse_address, parse_color, parse_quirks, parse_range, parse_syntax};
+use crate::{RunError, Runner, Stop};
+
+/// Arguments of every frontend, flattened in their own.
+#[derive(clap::Args)]
+// The doc comment would become the description of the frontends
+#[clap(about = None)]
+pub struct Args {
+    /// Path to the rom file
+    #[clap(parse(from_os_str))]
+    pub path: PathBuf,
+
+    /// Pixels color, in RGB format
+    #[clap(short, long, default_value = "0x00F0F0", parse(try_from_str = parse_color))]
+    pub pixel_color: u32,
+    /// Background color, in RGB format
+    #[clap(short, long, default_value = "0x000000", parse(try_from_str = parse_color))]
+    pub bg_color: u32,
+    /// Color of the pixels on the second XO-CHIP plane only, in RGB format
+    #[clap(long, default_value = "0xF0A000", parse(try_from_str = parse_color))]
+    pub plane2_color: u32,
+    /// Color of the pixels on both XO-CHIP planes, in RGB format
+    #[clap(long, default_value = "0xFFFFFF", parse(try_from_str = parse_color))]
+    pub blend_color: u32,
+
+    /// Behaviors of the ambiguous instructions, one of vip, chip48, schip or modern
+    #[clap(short, long, default_value = "modern", parse(try_from_str = parse_quirks))]
+    pub quirks: Quirks,
+
+    /// Instructions executed per frame, at 60 frames per second
+    #[clap(short, long, default_value = "15")]
+    pub ipf: usize,
+
+    /// Address where the ROM is loaded and starts, 0x600 for ETI-660 programs
+    #[clap(long, default_value = "0x200", parse(try_from_str = parse_address))]
+    pub load_address: u16,
+
+    /// Seed of the random generator, to make runs reproducible
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// Print the disassembly of the ROM instead of running it, in octo or classic syntax
+    #[clap(long, parse(try_from_str = parse_syntax))]
+    pub disasm: Option<Syntax>,
+    /// Print the hash of the ROM instead of running it, to give it its own keymap in cheap8_sdl
+    #[clap(long, conflicts_with = "disasm")]
+    pub rom_hash: bool,
+
+    /// Seconds of history kept to rewind, by holding backspace
+    #[clap(long, default_value = "10")]
+    pub rewind: usize,
+
+    /// Record the keys pressed to this movie file, to replay the run with --play
+    #[clap(long, conflicts_with = "debug", parse(from_os_str))]
+    pub record: Option<PathBuf>,
+    /// Replay a movie recorded with --record, with its seed and settings,
+    /// and keep running with the keyboard once it ends
+    #[clap(long, conflicts_with_all = &["debug", "record"], parse(from_os_str))]
+    pub play: Option<PathBuf>,
+
+    /// Start paused in a debugger, controlled by commands typed on stdin,
+    /// or under the screen after pressing Tab in cheap8_terminal
+    #[clap(long)]
+    pub debug: bool,
+
+    /// Log every executed instruction to this file
+    #[clap(long, conflicts_with = "debug", parse(from_os_str))]
+    pub trace: Option<PathBuf>,
+    /// Only trace the instructions in this address range, like 0x200-0x2FF
+    #[clap(long, requires = "trace", parse(try_from_str = parse_range))]
+    pub trace_range: Option<RangeInclusive<u16>>,
+    /// Stop tracing after this many lines
+    #[clap(long, requires = "trace")]
+    pub trace_lines: Option<u64>,
+}
+
+impl Args {
+    /// Colors of the pixel values in RGB: background,
+    /// first plane, second plane and both planes.
+    pub fn palette(&self) -> [(u8, u8, u8); 4] {
+        [
+            self.bg_color,
+            self.pixel_color,
+            self.plane2_color,
+            self.blend_color,
+        ]
+        .map(|color| ((color >> 16) as u8, (color >> 8) as u8, color as u8))
+    }
+}
+
+/// Create the runner of the ROM given in `args`, with its movie, rewind
+/// history and trace, or return `None` after printing the disassembly or
+/// the hash of the ROM instead. The errors are prefixed by what failed.
+pub fn setup(args: &Args) -> Result<Option<Runner>, String> {
+    let rom = fs::read(&args.path).map_err(|e| context("Failed to read the ROM", &e))?;
+    if let Some(syntax) = args.disasm {
+        let lines = disasm::listing(&rom, args.load_address);
+        print!("{}", disasm::format_listing(&lines, syntax));
+        return Ok(None);
+    }
+    if args.rom_hash {
+        println!("{:016x}", rom_hash(&rom));
+        return Ok(None);
+    }
+
+    let movie = match &args.play {
+        Some(path) => {
+            let bytes = fs::read(path).map_err(|e| context("Failed to read the movie", &e))?;
+            let movie =
+                Movie::from_bytes(&bytes).map_err(|e| context("Failed to read the movie", &e))?;
+            Some(movie)
+        }
+        None => None,
+    };
+
+    // The movie settings replace those of the command line
+    let mut cpu = match (&movie, args.seed) {
+        (Some(movie), _) => movie.cpu(),
+        (None, Some(seed)) => Cpu::with_seed(args.quirks, seed),
+        (None, None) => Cpu::new(args.quirks),
+    };
+    let ipf = movie.as_ref().map_or(args.ipf, |movie| movie.ipf);
+    if movie.is_none() {
+        cpu.set_load_address(args.load_address);
+        cpu.reset();
+    }
+
+    let mut runner = Runner::new(cpu, ipf);
+    // Kept by the runner to reset
+    runner
+        .load_rom(rom)
+        .map_err(|e| context("Failed to load the ROM", &e))?;
+    runner.set_rewind(args.rewind * 60);
+    if let Some(movie) = movie {
+        runner
+            .play(movie)
+            .map_err(|e| context("Failed to play the movie", &e))?;
+    }
+    if args.record.is_some() {
+        runner.record();
+    }
+    if let Some(path) = &args.trace {
+        let file = File::create(path).map_err(|e| context("Failed to create the trace", &e))?;
+        let mut tracer = Tracer::new(Box::new(BufWriter::new(file)) as Box<dyn Write>);
+        if let Some(range) = &args.trace_range {
+            tracer.set_range(range.clone());
+        }
+        tracer.set_max_lines(args.trace_lines);
+        runner.set_tracer(tracer);
+    }
+    Ok(Some(runner))
+}
+
+/// Write the movie recorded to the file given with --record, then
+/// flush the trace, even after the run failed with `result` to
+/// reproduce it. Return why the runner stopped and its CPU, or the
+/// errors one per line, prefixed by what failed like in [`setup`],
+/// `driver` being the context of the errors of the drivers.
+pub fn finish(
+    args: &Args,
+    runner: Runner,
+    result: Result<Stop, RunError>,
+    driver: &str,
+) -> Result<(Stop, Cpu), String> {
+    let result = result.map_err(|e| context(e.context(driver), &e));
+    let movie = match (&args.record, runner.movie()) {
+        (Some(path), Some(movie)) => {
+            fs::write(path, movie.to_bytes()).map_err(|e| context("Failed to write the movie", &e))
+        }
+        _ => Ok(()),
+    };
+    let cpu = runner
+        .finish()
+        .map_err(|e| context("Failed to write the trace", &e));
+    match (result, movie, cpu) {
+        (Ok(stop), Ok(()), Ok(cpu)) => Ok((stop, cpu)),
+        (result, movie, cpu) => {
+            let errors: Vec<_> = vec![result.err(), movie.err(), cpu.err()]
+                .into_iter()
+                .flatten()
+                .collect();
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+fn context(context: &str, error: &dyn Error) -> String {
+    format!("{}: {}", context, error)
+}
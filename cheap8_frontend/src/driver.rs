@@ -0,0 +1,119 @@
+use std::error::Error;
+
+use cheap8_core::{Cpu, CpuError};
+
+/// Key pressing each keypad key, 0 to F, by default: the 4x4 block on
+/// the left of a QWERTY keyboard, 1234/QWER/ASDF/ZXCV, laid out like
+/// the keypad of the COSMAC VIP.
+pub const DEFAULT_LAYOUT: [char; 16] = [
+    'X', '1', '2', '3', 'Q', 'W', 'E', 'A', 'S', 'D', 'Z', 'C', '4', 'R', 'F', 'V',
+];
+
+/// What the user did during a frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Input {
+    /// Keypad state, where `true` means pressed.
+    pub keys: [bool; 16],
+    /// True to go back one frame in the rewind history
+    /// instead of running one.
+    pub rewind: bool,
+    /// Hotkey pressed since the last frame, if any.
+    pub command: Option<Command>,
+    /// True to draw the screen again even if it didn't
+    /// change, for instance after the window was resized.
+    pub redraw: bool,
+}
+
+/// Controls of the emulator, usually bound to hotkeys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Pause, or resume if paused.
+    TogglePause,
+    /// Run one frame while paused.
+    Step,
+    /// Restart the ROM, see [`Runner::load_rom`](crate::Runner::load_rom).
+    Reset,
+    /// Execute more instructions per frame.
+    SpeedUp,
+    /// Execute fewer instructions per frame.
+    SpeedDown,
+    /// Switch the screen to fullscreen, or back to a window,
+    /// see [`VideoSink::toggle_fullscreen`].
+    ToggleFullscreen,
+}
+
+impl From<[bool; 16]> for Input {
+    fn from(keys: [bool; 16]) -> Self {
+        Input {
+            keys,
+            ..Input::default()
+        }
+    }
+}
+
+/// Where the screen is drawn.
+pub trait VideoSink {
+    /// Draw the `width` x `height` pixels of `screen`, row by row, each
+    /// pixel being the bitmask of the planes it is set on.
+    fn draw(&mut self, screen: &[u8], width: usize, height: usize) -> Result<(), Box<dyn Error>>;
+
+    /// Show `text` over the screen from the next draw on, or nothing
+    /// if `None`. By default it is ignored.
+    fn set_overlay(&mut self, _text: Option<&str>) {}
+
+    /// Switch to fullscreen, or back to a window. By default there
+    /// is no fullscreen mode and it is ignored.
+    fn toggle_fullscreen(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// Where the inputs come from.
+pub trait InputSource {
+    /// Read the input of the next frame, `None` if the user quit.
+    fn poll(&mut self) -> Result<Option<Input>, Box<dyn Error>>;
+}
+
+/// Where the sound is played.
+pub trait AudioSink {
+    /// Start or keep beeping, playing the XO-CHIP `pattern`
+    /// at `pitch` if there is one.
+    fn play(&mut self, pattern: Option<&[u8; 16]>, pitch: u8) -> Result<(), Box<dyn Error>>;
+
+    fn stop(&mut self);
+}
+
+/// No screen, for runs that only look at the final state.
+impl VideoSink for () {
+    fn draw(&mut self, _: &[u8], _: usize, _: usize) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// No sound.
+impl AudioSink for () {
+    fn play(&mut self, _: Option<&[u8; 16]>, _: u8) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn stop(&mut self) {}
+}
+
+/// Runs the frames in place of the [`Runner`](crate::Runner),
+/// to control the execution like a debugger does.
+pub trait Monitor {
+    /// Run a frame of `cpu`, or part of it, or nothing. Return
+    /// true iff the user quit.
+    fn frame(
+        &mut self,
+        cpu: &mut Cpu,
+        keys: &[bool; 16],
+        cycles_per_frame: usize,
+    ) -> Result<bool, CpuError>;
+
+    /// True iff the execution is paused, which silences it.
+    fn is_paused(&self) -> bool;
+
+    /// The program restarted from the beginning, see [`Command::Reset`].
+    fn reset(&mut self) {}
+}
@@ -0,0 +1,71 @@
+use std::error::Error;
+use std::fmt;
+
+use cheap8_core::{CpuError, LoadError, StateError};
+
+/// Error stopping a [`Runner`](crate::Runner), displayed as
+/// the underlying error for the frontends to give it context.
+#[derive(Debug)]
+pub enum RunError {
+    /// The CPU couldn't execute an instruction.
+    Cpu(CpuError),
+    /// A state of the rewind history couldn't be restored.
+    Rewind(StateError),
+    /// The ROM couldn't be loaded again on a reset.
+    Load(LoadError),
+    /// One of the drivers failed.
+    Driver(Box<dyn Error>),
+}
+
+impl RunError {
+    /// What failed, to prefix the error with, `driver`
+    /// being the context of the errors of the drivers.
+    pub fn context<'a>(&self, driver: &'a str) -> &'a str {
+        match self {
+            RunError::Cpu(_) => "The emulator stopped",
+            RunError::Rewind(_) => "Failed to rewind",
+            RunError::Load(_) => "Failed to reset",
+            RunError::Driver(_) => driver,
+        }
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunError::Cpu(e) => e.fmt(f),
+            RunError::Rewind(e) => e.fmt(f),
+            RunError::Load(e) => e.fmt(f),
+            RunError::Driver(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for RunError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RunError::Cpu(e) => Some(e),
+            RunError::Rewind(e) => Some(e),
+            RunError::Load(e) => Some(e),
+            RunError::Driver(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<CpuError> for RunError {
+    fn from(e: CpuError) -> Self {
+        RunError::Cpu(e)
+    }
+}
+
+impl From<StateError> for RunError {
+    fn from(e: StateError) -> Self {
+        RunError::Rewind(e)
+    }
+}
+
+impl From<LoadError> for RunError {
+    fn from(e: LoadError) -> Self {
+        RunError::Load(e)
+    }
+}
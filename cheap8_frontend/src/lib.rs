@@ -0,0 +1,15 @@
+//! # cheap8_frontend
+//! What the frontends of cheap8 share: the traits
+//! their drivers implement, the [`Runner`] driving the
+//! emulation with them, the command line arguments it is
+//! set up from and the debugger REPL.
+pub mod cli;
+mod driver;
+mod error;
+pub mod parse;
+pub mod repl;
+mod runner;
+
+pub use driver::{AudioSink, Command, Input, InputSource, Monitor, VideoSink, DEFAULT_LAYOUT};
+pub use error::RunError;
+pub use runner::{Runner, Stop};
@@ -0,0 +1,58 @@
+//! Parsers of the command line arguments the frontends have in common.
+use std::num::ParseIntError;
+use std::ops::RangeInclusive;
+
+use cheap8_core::disasm::Syntax;
+use cheap8_core::Quirks;
+
+/// Parse a color, like `0x00F0F0` in RGB.
+pub fn parse_color(src: &str) -> Result<u32, ParseIntError> {
+    match src.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => src.parse(),
+    }
+}
+
+/// Parse an address, in decimal or in hexadecimal with `0x`.
+pub fn parse_address(src: &str) -> Result<u16, ParseIntError> {
+    match src.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => src.parse(),
+    }
+}
+
+/// Parse a range of addresses, like `0x200-0x2FF`.
+pub fn parse_range(src: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = src
+        .split_once('-')
+        .ok_or_else(|| format!("expected a range like 0x200-0x2FF, got `{}`", src))?;
+    let start = parse_address(start).map_err(|e| e.to_string())?;
+    let end = parse_address(end).map_err(|e| e.to_string())?;
+    Ok(start..=end)
+}
+
+/// Parse the name of a quirks profile.
+pub fn parse_quirks(src: &str) -> Result<Quirks, String> {
+    match src {
+        "vip" => Ok(Quirks::cosmac_vip()),
+        "chip48" => Ok(Quirks::chip48()),
+        "schip" => Ok(Quirks::super_chip()),
+        "modern" => Ok(Quirks::modern()),
+        _ => Err(format!(
+            "unknown profile `{}`, expected vip, chip48, schip or modern",
+            src
+        )),
+    }
+}
+
+/// Parse the name of a disassembly syntax.
+pub fn parse_syntax(src: &str) -> Result<Syntax, String> {
+    match src {
+        "octo" => Ok(Syntax::Octo),
+        "classic" => Ok(Syntax::Classic),
+        _ => Err(format!(
+            "unknown syntax `{}`, expected octo or classic",
+            src
+        )),
+    }
+}
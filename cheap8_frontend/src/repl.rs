@@ -0,0 +1,425 @@
+//! Debugger REPL, reading commands like `break 0x208` or `step 4`
+//! line by line and printing the state of the CPU.
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use crate::Monitor;
+use cheap8_core::debugger::{
+    Access, Breakpoint, Comparison, Condition, Debugger, Stop, Watchpoint,
+};
+use cheap8_core::disasm::{self, Syntax};
+use cheap8_core::{decode, Cpu, CpuError};
+
+const HELP: &str = "\
+Commands:
+  s, step [N]               execute N instructions (1)
+  n, next                   step over subroutine calls
+  finish                    run until the current subroutine returns
+  u, until ADDR             run until the PC reaches ADDR
+  c, continue               run until a breakpoint or watchpoint
+  p, pause                  stop running
+  b, break ADDR [if vX OP NN]
+                            break at ADDR, OP being ==, !=, < or >
+  w, watch ADDR[-END] [r|w|rw]
+                            break on reads and/or writes of ADDR to END (rw)
+  d, delete b|w N           delete breakpoint or watchpoint N
+  i, info                   list the breakpoints and watchpoints
+  r, regs                   show the registers
+  x ADDR [LEN]              dump LEN bytes of memory at ADDR (16)
+  l, list [ADDR] [N]        disassemble N instructions from ADDR (PC, 8)
+  q, quit                   exit the emulator
+An empty line repeats the last command.";
+
+/// A command typed in the debugger REPL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Step(usize),
+    Next,
+    Finish,
+    Until(u16),
+    Continue,
+    Pause,
+    Break(Breakpoint),
+    Watch(Watchpoint),
+    Delete { watchpoint: bool, index: usize },
+    Info,
+    Registers,
+    Memory { address: u16, len: usize },
+    List { address: Option<u16>, count: usize },
+    Help,
+    Quit,
+}
+
+/// Debugging session, driven by the lines of commands it receives.
+pub struct Session {
+    debugger: Debugger,
+    lines: Receiver<String>,
+    // Command repeated by an empty line.
+    last: Option<Command>,
+    output: Box<dyn Write>,
+    paused: bool,
+}
+
+impl Session {
+    /// Start reading commands on stdin and printing on stdout, the
+    /// program is paused until the first run command.
+    pub fn new(cycles_per_frame: usize) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self::with_io(cycles_per_frame, lines, Box::new(io::stdout()))
+    }
+
+    /// Same as [`Session::new`], but the commands are read
+    /// from `lines` and printed to `output`.
+    pub fn with_io(
+        cycles_per_frame: usize,
+        lines: Receiver<String>,
+        output: Box<dyn Write>,
+    ) -> Self {
+        let mut session = Session {
+            debugger: Debugger::new(cycles_per_frame),
+            lines,
+            last: None,
+            output,
+            paused: true,
+        };
+        session.print("Paused, type `help` for the list of commands.\n");
+        session
+    }
+
+    // Write `text` to the output, which is only for the user to read.
+    fn print(&mut self, text: &str) {
+        let _ = self.output.write_all(text.as_bytes());
+        let _ = self.output.flush();
+    }
+
+    fn handle(&mut self, cpu: &Cpu, command: Command) {
+        match command {
+            Command::Step(count) => self.run(|debugger| debugger.step(count)),
+            Command::Next => self.run(|debugger| debugger.step_over(cpu)),
+            Command::Finish => self.run(|debugger| debugger.step_out(cpu)),
+            Command::Until(address) => self.run(|debugger| debugger.run_to(address)),
+            Command::Continue => self.run(Debugger::resume),
+            Command::Pause => {
+                if !self.paused {
+                    self.paused = true;
+                    self.print(&list(cpu, cpu.pc(), 1));
+                }
+            }
+            Command::Break(breakpoint) => {
+                self.debugger.add_breakpoint(breakpoint);
+                let index = self.debugger.breakpoints().len() - 1;
+                self.print(&format!("Breakpoint {}\n", index));
+            }
+            Command::Watch(watchpoint) => {
+                self.debugger.add_watchpoint(watchpoint);
+                let index = self.debugger.watchpoints().len() - 1;
+                self.print(&format!("Watchpoint {}\n", index));
+            }
+            Command::Delete { watchpoint, index } => {
+                let deleted = if watchpoint {
+                    self.debugger.remove_watchpoint(index).is_some()
+                } else {
+                    self.debugger.remove_breakpoint(index).is_some()
+                };
+                if !deleted {
+                    self.print("No such breakpoint or watchpoint\n");
+                }
+            }
+            Command::Info => {
+                let info = self.info();
+                self.print(&info);
+            }
+            Command::Registers => self.print(&registers(cpu)),
+            Command::Memory { address, len } => self.print(&memory(cpu, address, len)),
+            Command::List { address, count } => {
+                self.print(&list(cpu, address.unwrap_or(cpu.pc()), count))
+            }
+            Command::Help => self.print(&format!("{}\n", HELP)),
+            Command::Quit => (),
+        }
+    }
+
+    fn run(&mut self, goal: impl FnOnce(&mut Debugger)) {
+        goal(&mut self.debugger);
+        self.paused = false;
+    }
+
+    // List of the breakpoints and watchpoints.
+    fn info(&self) -> String {
+        let mut out = String::new();
+        for (i, breakpoint) in self.debugger.breakpoints().iter().enumerate() {
+            out += &format!("b{}  {:#06x}", i, breakpoint.address);
+            if let Some(condition) = breakpoint.condition {
+                let comparison = match condition.comparison {
+                    Comparison::Equal => "==",
+                    Comparison::NotEqual => "!=",
+                    Comparison::Less => "<",
+                    Comparison::Greater => ">",
+                };
+                out += &format!(
+                    " if v{:x} {} {:#04x}",
+                    condition.register, comparison, condition.value
+                );
+            }
+            out.push('\n');
+        }
+        for (i, watchpoint) in self.debugger.watchpoints().iter().enumerate() {
+            let access = match (watchpoint.read, watchpoint.write) {
+                (true, true) => "rw",
+                (true, false) => "r",
+                _ => "w",
+            };
+            out += &format!(
+                "w{}  {:#06x}-{:#06x} {}\n",
+                i,
+                watchpoint.range.start(),
+                watchpoint.range.end(),
+                access
+            );
+        }
+        out
+    }
+}
+
+impl Monitor for Session {
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn reset(&mut self) {
+        self.debugger.restart();
+    }
+
+    /// Handle the pending commands, then run one frame unless paused.
+    /// Return true iff the user quit.
+    fn frame(
+        &mut self,
+        cpu: &mut Cpu,
+        key_pressed: &[bool; 16],
+        cycles_per_frame: usize,
+    ) -> Result<bool, CpuError> {
+        while let Ok(line) = self.lines.try_recv() {
+            let command = match (line.trim(), &self.last) {
+                ("", Some(last)) => Ok(last.clone()),
+                (line, _) => parse(line),
+            };
+            match command {
+                Ok(Command::Quit) => return Ok(true),
+                Ok(command) => {
+                    self.last = Some(command.clone());
+                    self.handle(cpu, command);
+                }
+                Err(e) => self.print(&format!("{}\n", e)),
+            }
+        }
+        if self.paused {
+            return Ok(false);
+        }
+
+        // The speed may have changed since the last frame
+        self.debugger.set_cycles_per_frame(cycles_per_frame);
+        let stop = self.debugger.run(cpu, key_pressed, cycles_per_frame)?;
+        match stop {
+            Stop::Budget => return Ok(false),
+            Stop::Done => (),
+            Stop::Breakpoint { address } => {
+                self.print(&format!("Breakpoint at {:#06x}\n", address))
+            }
+            Stop::Watchpoint {
+                address,
+                access,
+                location,
+            } => {
+                let access = match access {
+                    Access::Read => "read",
+                    Access::Write => "write",
+                };
+                self.print(&format!(
+                    "Watchpoint: {:#06x} {} {:#06x}\n",
+                    address, access, location
+                ));
+            }
+            Stop::Exit => self.print("The program exited\n"),
+        }
+        self.paused = true;
+        self.print(&list(cpu, cpu.pc(), 1));
+        Ok(false)
+    }
+}
+
+/// Parse a line typed in the REPL.
+pub fn parse(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+    let arg = |i: usize| args.get(i).copied();
+    let required = |i: usize| arg(i).ok_or_else(|| format!("`{}` needs more arguments", command));
+
+    let command = match command {
+        "s" | "step" => Command::Step(arg(0).map(number).transpose()?.unwrap_or(1) as usize),
+        "n" | "next" => Command::Next,
+        "finish" => Command::Finish,
+        "u" | "until" => Command::Until(address(required(0)?)?),
+        "c" | "continue" => Command::Continue,
+        "p" | "pause" => Command::Pause,
+        "b" | "break" => {
+            let condition = match args.get(1..) {
+                Some([]) | None => None,
+                Some(["if", register, comparison, value]) => Some(Condition {
+                    register: self::register(register)?,
+                    comparison: match *comparison {
+                        "==" => Comparison::Equal,
+                        "!=" => Comparison::NotEqual,
+                        "<" => Comparison::Less,
+                        ">" => Comparison::Greater,
+                        _ => return Err(format!("unknown comparison `{}`", comparison)),
+                    },
+                    value: byte(value)?,
+                }),
+                _ => return Err("expected `break ADDR [if vX OP NN]`".to_string()),
+            };
+            Command::Break(Breakpoint {
+                address: address(required(0)?)?,
+                condition,
+            })
+        }
+        "w" | "watch" => {
+            let range = required(0)?;
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (address(start)?, address(end)?),
+                None => (address(range)?, address(range)?),
+            };
+            if start > end {
+                return Err("the watched range is empty".to_string());
+            }
+            let (read, write) = match arg(1).unwrap_or("rw") {
+                "r" => (true, false),
+                "w" => (false, true),
+                "rw" => (true, true),
+                access => return Err(format!("unknown access `{}`, expected r, w or rw", access)),
+            };
+            Command::Watch(Watchpoint {
+                range: start..=end,
+                read,
+                write,
+            })
+        }
+        "d" | "delete" => Command::Delete {
+            watchpoint: match required(0)? {
+                "b" => false,
+                "w" => true,
+                kind => return Err(format!("unknown kind `{}`, expected b or w", kind)),
+            },
+            index: number(required(1)?)? as usize,
+        },
+        "i" | "info" => Command::Info,
+        "r" | "regs" => Command::Registers,
+        "x" => Command::Memory {
+            address: address(required(0)?)?,
+            len: arg(1).map(number).transpose()?.unwrap_or(16) as usize,
+        },
+        "l" | "list" => Command::List {
+            address: arg(0).map(address).transpose()?,
+            count: arg(1).map(number).transpose()?.unwrap_or(8) as usize,
+        },
+        "h" | "help" => Command::Help,
+        "q" | "quit" => Command::Quit,
+        _ => return Err(format!("unknown command `{}`, see `help`", command)),
+    };
+
+    Ok(command)
+}
+
+fn number(src: &str) -> Result<u32, String> {
+    let parsed = match src.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => src.parse(),
+    };
+    parsed.map_err(|e| format!("invalid number `{}`: {}", src, e))
+}
+
+fn address(src: &str) -> Result<u16, String> {
+    u16::try_from(number(src)?).map_err(|_| format!("address `{}` out of memory", src))
+}
+
+fn byte(src: &str) -> Result<u8, String> {
+    u8::try_from(number(src)?).map_err(|_| format!("`{}` doesn't fit in a byte", src))
+}
+
+fn register(src: &str) -> Result<u8, String> {
+    src.strip_prefix(['v', 'V'])
+        .and_then(|x| u8::from_str_radix(x, 16).ok())
+        .filter(|x| *x < 16)
+        .ok_or_else(|| format!("invalid register `{}`", src))
+}
+
+fn registers(cpu: &Cpu) -> String {
+    let mut out = format!(
+        "pc {:#06x}  i {:#06x}  dt {}  st {}\n",
+        cpu.pc(),
+        cpu.index(),
+        cpu.delay_timer(),
+        cpu.sound_timer()
+    );
+    for (row, values) in cpu.registers().chunks(8).enumerate() {
+        for (x, value) in values.iter().enumerate() {
+            out += &format!("v{:x} {:02x}  ", row * 8 + x, value);
+        }
+        out.push('\n');
+    }
+    out += "stack";
+    for address in cpu.stack() {
+        out += &format!(" {:#06x}", address);
+    }
+    out.push('\n');
+    out
+}
+
+fn memory(cpu: &Cpu, address: u16, len: usize) -> String {
+    let start = address as usize;
+    let end = (start + len).min(cpu.memory().len());
+    let mut out = String::new();
+    for (row, bytes) in cpu.memory()[start..end].chunks(16).enumerate() {
+        out += &format!("{:#06x} ", start + row * 16);
+        for byte in bytes {
+            out += &format!(" {:02x}", byte);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Disassembly of the `count` instructions from `address`, following
+// the memory in order rather than the control flow.
+fn list(cpu: &Cpu, address: u16, count: usize) -> String {
+    let memory = cpu.memory();
+    let mut address = address as usize;
+    let mut out = String::new();
+    for _ in 0..count {
+        let bytes = match memory.get(address..address + 2) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+            None => break,
+        };
+        let marker = if address == cpu.pc() as usize {
+            ">"
+        } else {
+            " "
+        };
+        let text = match decode(bytes) {
+            Ok(instruction) => disasm::format(&instruction, Syntax::Octo),
+            Err(_) => format!("0x{:02X} 0x{:02X}", bytes >> 8, bytes & 0xFF),
+        };
+        out += &format!("{} {:#06x}  {:04X}  {}\n", marker, address, bytes, text);
+        address += 2;
+    }
+    out
+}
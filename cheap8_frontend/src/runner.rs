@@ -0,0 +1,378 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cheap8_core::debugger::current_instruction;
+use cheap8_core::movie::Movie;
+use cheap8_core::rewind::Rewind;
+use cheap8_core::trace::Tracer;
+use cheap8_core::{Cpu, CpuError, Instruction, LoadError, MovieError};
+
+use crate::driver::{AudioSink, Command, InputSource, Monitor, VideoSink};
+use crate::error::RunError;
+
+// Instructions per frame to choose from with
+// `Command::SpeedUp` and `Command::SpeedDown`.
+const SPEEDS: [usize; 18] = [
+    1, 2, 3, 5, 7, 10, 15, 20, 30, 50, 70, 100, 150, 200, 300, 500, 700, 1000,
+];
+// Frames a message stays over the screen.
+const MESSAGE_FRAMES: u32 = 120;
+
+/// Why a [`Runner`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The user quit.
+    Quit,
+    /// The program exited (with 00FD).
+    Exit,
+    /// The maximum number of frames ran.
+    FrameLimit,
+    /// The program jumped to itself at `address`,
+    /// see [`Runner::set_stop_on_loop`].
+    Loop { address: u16 },
+}
+
+/// Runs a [`Cpu`] frame after frame, 60 times per second, reading the
+/// keys from an [`InputSource`] and sending the screen and sound to a
+/// [`VideoSink`] and an [`AudioSink`]. Each frame is recorded in a
+/// rewind history, and optionally in a movie or a trace. The
+/// [`Command`]s of the inputs pause, restart it or change its speed.
+pub struct Runner {
+    cpu: Cpu,
+    ipf: usize,
+    // ROM reloaded on reset, see `load_rom`.
+    rom: Option<Vec<u8>>,
+    paused: bool,
+    // Message shown over the screen, and for how many more frames.
+    message: Option<(String, u32)>,
+    // Text shown over the screen since the last change.
+    overlay: Option<String>,
+    throttle: bool,
+    max_frames: Option<u64>,
+    stop_on_loop: bool,
+    rewind: Rewind,
+    // Movie whose keys replace the inputs, while it lasts.
+    playing: Option<Movie>,
+    recording: Option<Movie>,
+    tracer: Option<Tracer<Box<dyn Write>>>,
+    monitor: Option<Box<dyn Monitor>>,
+}
+
+impl Runner {
+    /// Create a runner for `cpu`, which has its ROM loaded, executing
+    /// `ipf` instructions per frame. It runs in real time without
+    /// any limit, and keeps no rewind history.
+    pub fn new(cpu: Cpu, ipf: usize) -> Self {
+        Runner {
+            cpu,
+            ipf,
+            rom: None,
+            paused: false,
+            message: None,
+            overlay: None,
+            throttle: true,
+            max_frames: None,
+            stop_on_loop: false,
+            rewind: Rewind::new(0),
+            playing: None,
+            recording: None,
+            tracer: None,
+            monitor: None,
+        }
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn ipf(&self) -> usize {
+        self.ipf
+    }
+
+    /// Load `rom` in the CPU, and keep it to restart
+    /// it on [`Command::Reset`], which is ignored otherwise.
+    pub fn load_rom(&mut self, rom: Vec<u8>) -> Result<(), LoadError> {
+        self.cpu.load_bytes(&rom)?;
+        self.rom = Some(rom);
+        Ok(())
+    }
+
+    /// Wait between the frames to run 60 of them per second if
+    /// true, the default, or run as fast as possible.
+    pub fn set_throttle(&mut self, throttle: bool) {
+        self.throttle = throttle;
+    }
+
+    /// Stop once the CPU ran `max_frames` frames, `None` for no limit.
+    pub fn set_max_frames(&mut self, max_frames: Option<u64>) {
+        self.max_frames = max_frames;
+    }
+
+    /// Stop as soon as the program jumps to itself, which
+    /// is how most programs end.
+    pub fn set_stop_on_loop(&mut self, stop_on_loop: bool) {
+        self.stop_on_loop = stop_on_loop;
+    }
+
+    /// Keep the last `frames` frames to rewind.
+    pub fn set_rewind(&mut self, frames: usize) {
+        self.rewind = Rewind::new(frames);
+        self.rewind.push(&self.cpu);
+    }
+
+    /// Trace the executed instructions with `tracer`.
+    pub fn set_tracer(&mut self, tracer: Tracer<Box<dyn Write>>) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Let `monitor` run the frames, tracing is then disabled.
+    pub fn set_monitor(&mut self, monitor: Box<dyn Monitor>) {
+        self.monitor = Some(monitor);
+    }
+
+    /// Replay `movie`, the CPU having been created by [`Movie::cpu`],
+    /// and switch to the inputs once it ends.
+    pub fn play(&mut self, movie: Movie) -> Result<(), MovieError> {
+        movie.check_rom(&self.cpu)?;
+        self.playing = Some(movie);
+        Ok(())
+    }
+
+    /// Record the inputs of the next frames in a movie,
+    /// see [`Runner::movie`].
+    pub fn record(&mut self) {
+        self.recording = Some(Movie::new(&self.cpu, self.ipf));
+    }
+
+    /// The movie being recorded, if any.
+    pub fn movie(&self) -> Option<&Movie> {
+        self.recording.as_ref()
+    }
+
+    /// Run frames until the user quits, the program exits or one of
+    /// the limits set is reached. The sound is stopped when it returns.
+    pub fn run<V, I, A>(
+        &mut self,
+        video: &mut V,
+        input: &mut I,
+        audio: &mut A,
+    ) -> Result<Stop, RunError>
+    where
+        V: VideoSink + ?Sized,
+        I: InputSource + ?Sized,
+        A: AudioSink + ?Sized,
+    {
+        let result = self.run_frames(video, input, audio);
+        audio.stop();
+        result
+    }
+
+    /// Flush the trace, and give back the CPU.
+    pub fn finish(self) -> io::Result<Cpu> {
+        if let Some(tracer) = self.tracer {
+            tracer.finish()?;
+        }
+        Ok(self.cpu)
+    }
+
+    fn run_frames<V, I, A>(
+        &mut self,
+        video: &mut V,
+        input: &mut I,
+        audio: &mut A,
+    ) -> Result<Stop, RunError>
+    where
+        V: VideoSink + ?Sized,
+        I: InputSource + ?Sized,
+        A: AudioSink + ?Sized,
+    {
+        let frame = Duration::from_secs(1) / 60;
+        let mut next_frame = Instant::now();
+        loop {
+            if self.max_frames.is_some_and(|max| self.cpu.frames() >= max) {
+                return Ok(Stop::FrameLimit);
+            }
+            let input = match input.poll().map_err(RunError::Driver)? {
+                Some(input) => input,
+                None => return Ok(Stop::Quit),
+            };
+            let redraw = match input.command {
+                Some(command) => self.command(command, video)?,
+                None => false,
+            } || input.redraw;
+
+            let screen_update = if input.rewind {
+                self.rewind.step_back(&mut self.cpu)?;
+                true
+            } else if self.paused && input.command != Some(Command::Step) {
+                redraw
+            } else {
+                match self.frame(input.keys)? {
+                    Some(screen_update) => screen_update || redraw,
+                    None => return Ok(Stop::Quit),
+                }
+            };
+            let overlay = self.overlay_text();
+            let overlay_update = overlay != self.overlay;
+            if overlay_update {
+                video.set_overlay(overlay.as_deref());
+                self.overlay = overlay;
+            }
+            let output = self.cpu.output();
+            if screen_update || overlay_update {
+                video
+                    .draw(output.screen, output.width, output.height)
+                    .map_err(RunError::Driver)?;
+            }
+            if output.exit {
+                return Ok(Stop::Exit);
+            }
+            if self.stop_on_loop && self.is_looping() {
+                return Ok(Stop::Loop {
+                    address: self.cpu.pc(),
+                });
+            }
+
+            let paused =
+                input.rewind || self.paused || self.monitor.as_ref().is_some_and(|m| m.is_paused());
+            if output.beep && !paused {
+                audio
+                    .play(output.audio_pattern, output.pitch)
+                    .map_err(RunError::Driver)?;
+            } else {
+                audio.stop();
+            }
+
+            if self.throttle {
+                next_frame += frame;
+                match next_frame.checked_duration_since(Instant::now()) {
+                    Some(delay) => thread::sleep(delay),
+                    // Too late, don't try to catch up
+                    None => next_frame = Instant::now(),
+                }
+            }
+        }
+    }
+
+    // Execute `command`, return true iff the screen must be drawn again.
+    fn command<V>(&mut self, command: Command, video: &mut V) -> Result<bool, RunError>
+    where
+        V: VideoSink + ?Sized,
+    {
+        match command {
+            Command::TogglePause => self.paused = !self.paused,
+            Command::Step => (),
+            Command::Reset => {
+                // A movie only holds the keys from the start
+                if self.playing.is_some() || self.recording.is_some() {
+                    self.show("NO RESET (MOVIE)");
+                    return Ok(false);
+                }
+                if let Some(rom) = &self.rom {
+                    self.cpu.reset();
+                    // The load address may have changed since `load_rom`
+                    self.cpu.load_bytes(rom)?;
+                    // Don't rewind to before the reset
+                    self.rewind.clear();
+                    self.rewind.push(&self.cpu);
+                    if let Some(monitor) = &mut self.monitor {
+                        monitor.reset();
+                    }
+                    self.show("RESET");
+                    return Ok(true);
+                }
+            }
+            Command::SpeedUp | Command::SpeedDown => {
+                // A movie is replayed with the instructions per frame it was recorded with
+                if self.playing.is_some() || self.recording.is_some() {
+                    self.show(&format!("IPF {} (MOVIE)", self.ipf));
+                    return Ok(false);
+                }
+                let speed = if command == Command::SpeedUp {
+                    SPEEDS.iter().find(|speed| **speed > self.ipf)
+                } else {
+                    SPEEDS.iter().rev().find(|speed| **speed < self.ipf)
+                };
+                self.ipf = speed.copied().unwrap_or(self.ipf);
+                self.show(&format!("IPF {}", self.ipf));
+            }
+            Command::ToggleFullscreen => {
+                video.toggle_fullscreen().map_err(RunError::Driver)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // Show `message` over the screen for a while.
+    fn show(&mut self, message: &str) {
+        self.message = Some((message.to_string(), MESSAGE_FRAMES));
+    }
+
+    // Text to show over the screen this frame, one line per state or message.
+    fn overlay_text(&mut self) -> Option<String> {
+        let message = match &mut self.message {
+            Some((_, 0)) => {
+                self.message = None;
+                None
+            }
+            Some((message, frames)) => {
+                *frames -= 1;
+                Some(message.as_str())
+            }
+            None => None,
+        };
+        match (self.paused, message) {
+            (true, Some(message)) => Some(format!("PAUSED\n{}", message)),
+            (true, None) => Some("PAUSED".to_string()),
+            (false, Some(message)) => Some(message.to_string()),
+            (false, None) => None,
+        }
+    }
+
+    // Run one frame with `keys`, unless a movie is playing, and record
+    // it. Return whether the screen changed, or `None` if the user quit
+    // from the monitor.
+    fn frame(&mut self, keys: [bool; 16]) -> Result<Option<bool>, CpuError> {
+        let frame = self.cpu.frames();
+        let keys = self
+            .playing
+            .as_ref()
+            .and_then(|movie| movie.keys(frame))
+            .unwrap_or(keys);
+        if let Some(movie) = &mut self.recording {
+            movie.record(frame, &keys);
+        }
+
+        let cycles = self.cpu.cycles();
+        let screen_update = match (&mut self.monitor, &mut self.tracer) {
+            (Some(monitor), _) => {
+                if monitor.frame(&mut self.cpu, &keys, self.ipf)? {
+                    return Ok(None);
+                }
+                // It may stop in the middle of a frame, redraw if it ran
+                self.cpu.cycles() != cycles
+            }
+            (None, Some(tracer)) => {
+                self.cpu
+                    .run_frame_with(&keys, self.ipf, |cpu| tracer.trace(cpu))?
+                    .screen_update
+            }
+            (None, None) => self.cpu.run_frame(&keys, self.ipf)?.screen_update,
+        };
+        // Don't fill the history while paused
+        if self.cpu.cycles() != cycles {
+            self.rewind.push(&self.cpu);
+        }
+        Ok(Some(screen_update))
+    }
+
+    // True iff the instruction at the PC jumps to itself.
+    fn is_looping(&self) -> bool {
+        matches!(
+            current_instruction(&self.cpu),
+            Some(Instruction::Jump { nnn }) if nnn == self.cpu.pc()
+        )
+    }
+}
@@ -0,0 +1,90 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use cheap8_frontend::cli::{self, Args};
+use cheap8_frontend::{Input, InputSource};
+
+#[derive(Parser)]
+struct Cli {
+    #[clap(flatten)]
+    common: Args,
+}
+
+// Write `rom` to a file named after the test, and parse the
+// command line `args` given after its path.
+fn args(name: &str, rom: &[u8], args: &[&str]) -> Args {
+    let path = temp_path(name, "ch8");
+    fs::write(&path, rom).unwrap();
+    let path = path.to_str().unwrap();
+    let mut line = vec!["cheap8", path];
+    line.extend(args);
+    Cli::try_parse_from(line).unwrap().common
+}
+
+fn temp_path(name: &str, extension: &str) -> PathBuf {
+    env::temp_dir()
+        .join(format!("cheap8_cli_{}", name))
+        .with_extension(extension)
+}
+
+// Input pressing no key, forever.
+struct NoKeys;
+
+impl InputSource for NoKeys {
+    fn poll(&mut self) -> Result<Option<Input>, Box<dyn Error>> {
+        Ok(Some(Input::default()))
+    }
+}
+
+#[test]
+fn setup_from_args() {
+    let rom = [0x60, 0x2A, 0x16, 0x02];
+    let hash = args("hash", &rom, &["--rom-hash"]);
+    assert!(cli::setup(&hash).unwrap().is_none());
+
+    let args = args(
+        "setup",
+        &rom,
+        &["--seed", "5", "--load-address", "0x600", "-i", "7"],
+    );
+    let runner = cli::setup(&args).unwrap().unwrap();
+    assert_eq!(runner.cpu().pc(), 0x600);
+    assert_eq!(runner.cpu().seed(), 5);
+    assert_eq!(runner.cpu().memory()[0x600..0x604], rom);
+    assert_eq!(runner.ipf(), 7);
+    assert!(runner.movie().is_none());
+}
+
+#[test]
+fn errors_are_all_reported() {
+    let mut missing = args("missing", &[], &[]);
+    missing.path = temp_path("missing", "none");
+    let error = cli::setup(&missing).err().unwrap();
+    assert!(error.starts_with("Failed to read the ROM: "), "{}", error);
+
+    // An unknown opcode, recorded in a directory that doesn't exist
+    let movie = temp_path("none", "dir").join("movie.c8mv");
+    let movie = movie.to_str().unwrap();
+    let args = args("errors", &[0xE0, 0x00], &["--record", movie]);
+    let mut runner = cli::setup(&args).unwrap().unwrap();
+    runner.set_throttle(false);
+    let result = runner.run(&mut (), &mut NoKeys, &mut ());
+    let error = cli::finish(&args, runner, result, "The driver failed")
+        .err()
+        .unwrap();
+    let lines: Vec<_> = error.lines().collect();
+    assert_eq!(
+        lines[0],
+        "The emulator stopped: unknown opcode 0xe000 at 0x200"
+    );
+    assert!(
+        lines[1].starts_with("Failed to write the movie: "),
+        "{}",
+        error
+    );
+    assert_eq!(lines.len(), 2);
+}
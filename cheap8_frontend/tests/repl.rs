@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::sync::mpsc;
+
+use cheap8_core::{Cpu, Quirks};
+use cheap8_frontend::repl::{parse, Command, Session};
+use cheap8_frontend::Monitor;
+
+// Output of a session, kept to be read by the test.
+#[derive(Clone, Default)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    fn take(&self) -> String {
+        String::from_utf8(self.0.take()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn commands_are_parsed() {
+    assert_eq!(parse("s 3"), Ok(Command::Step(3)));
+    assert_eq!(
+        parse("x 0x300"),
+        Ok(Command::Memory {
+            address: 0x300,
+            len: 16
+        })
+    );
+    assert_eq!(
+        parse("jump"),
+        Err("unknown command `jump`, see `help`".to_string())
+    );
+}
+
+#[test]
+fn session_stops_at_breakpoints() {
+    // Increment V0 in loop
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 0);
+    cpu.reset();
+    cpu.load_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+    let (sender, lines) = mpsc::channel();
+    let output = Output::default();
+    let mut session = Session::with_io(10, lines, Box::new(output.clone()));
+    assert!(session.is_paused());
+    assert_eq!(
+        output.take(),
+        "Paused, type `help` for the list of commands.\n"
+    );
+
+    for line in ["b 0x202", "c"] {
+        sender.send(line.to_string()).unwrap();
+    }
+    assert!(!session.frame(&mut cpu, &[false; 16], 10).unwrap());
+    assert!(session.is_paused());
+    assert_eq!(
+        output.take(),
+        "Breakpoint 0\nBreakpoint at 0x0202\n> 0x0202  1200  jump 0x200\n"
+    );
+
+    // An empty line repeats the last command
+    sender.send(String::new()).unwrap();
+    assert!(!session.frame(&mut cpu, &[false; 16], 10).unwrap());
+    assert_eq!(cpu.registers()[0], 2);
+    output.take();
+
+    for line in ["nope", "q"] {
+        sender.send(line.to_string()).unwrap();
+    }
+    assert!(session.frame(&mut cpu, &[false; 16], 10).unwrap());
+    assert_eq!(output.take(), "unknown command `nope`, see `help`\n");
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+use std::error::Error;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use cheap8_core::{Cpu, CpuError, Quirks};
+use cheap8_frontend::{Command, Input, InputSource, Monitor, Runner, Stop, VideoSink};
+
+// Inputs given one per frame, then quits.
+struct Script(VecDeque<Input>);
+
+impl Script {
+    fn new(commands: &[Option<Command>]) -> Self {
+        Script(
+            commands
+                .iter()
+                .map(|command| Input {
+                    command: *command,
+                    ..Input::default()
+                })
+                .collect(),
+        )
+    }
+}
+
+impl InputSource for Script {
+    fn poll(&mut self) -> Result<Option<Input>, Box<dyn Error>> {
+        Ok(self.0.pop_front())
+    }
+}
+
+// Records the texts shown over the screen.
+#[derive(Default)]
+struct Overlays(Vec<Option<String>>);
+
+impl VideoSink for Overlays {
+    fn draw(&mut self, _: &[u8], _: usize, _: usize) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn set_overlay(&mut self, text: Option<&str>) {
+        self.0.push(text.map(str::to_string));
+    }
+}
+
+// Runner of a ROM incrementing V0 in loop.
+fn runner() -> Runner {
+    let mut cpu = Cpu::with_seed(Quirks::modern(), 0);
+    cpu.reset();
+    let mut runner = Runner::new(cpu, 10);
+    runner.set_throttle(false);
+    runner.load_rom(vec![0x70, 0x01, 0x12, 0x00]).unwrap();
+    runner
+}
+
+#[test]
+fn pause_and_step() {
+    use Command::*;
+    let mut runner = runner();
+    let mut video = Overlays::default();
+    let mut script = Script::new(&[
+        Some(TogglePause),
+        None,
+        Some(Step),
+        None,
+        Some(TogglePause),
+        None,
+    ]);
+    let stop = runner.run(&mut video, &mut script, &mut ()).unwrap();
+    assert_eq!(stop, Stop::Quit);
+    assert_eq!(runner.cpu().frames(), 3);
+    assert_eq!(video.0, [Some("PAUSED".to_string()), None]);
+}
+
+#[test]
+fn reset_and_speed() {
+    use Command::*;
+    let mut runner = runner();
+    let mut script = Script::new(&[None, Some(SpeedUp), None, Some(SpeedDown), Some(SpeedDown)]);
+    runner.run(&mut (), &mut script, &mut ()).unwrap();
+    assert_eq!(runner.ipf(), 7);
+    assert_eq!(runner.cpu().frames(), 5);
+    // 10, 15, 15, 10 and 7 instructions, every other one adding 1
+    assert_eq!(runner.cpu().registers()[0], 29);
+
+    let mut script = Script::new(&[Some(Reset)]);
+    runner.run(&mut (), &mut script, &mut ()).unwrap();
+    assert_eq!(runner.cpu().frames(), 1);
+    assert_eq!(runner.cpu().registers()[0], 4);
+}
+
+#[test]
+fn no_rewind_past_reset() {
+    let mut runner = runner();
+    runner.set_rewind(10);
+    let mut script = Script::new(&[None, None, Some(Command::Reset), None, None]);
+    script.0[3].rewind = true;
+    script.0[4].rewind = true;
+    runner.run(&mut (), &mut script, &mut ()).unwrap();
+    assert_eq!(runner.cpu().frames(), 0);
+    assert_eq!(runner.cpu().registers()[0], 0);
+}
+
+#[test]
+fn no_reset_in_movie() {
+    let mut runner = runner();
+    runner.record();
+    let mut video = Overlays::default();
+    let mut script = Script::new(&[None, Some(Command::Reset)]);
+    runner.run(&mut video, &mut script, &mut ()).unwrap();
+    assert_eq!(runner.cpu().frames(), 2);
+    assert_eq!(video.0, [Some("NO RESET (MOVIE)".to_string())]);
+}
+
+// Counts the screens drawn and the fullscreen toggles.
+#[derive(Default)]
+struct Window {
+    draws: usize,
+    fullscreen: bool,
+}
+
+impl VideoSink for Window {
+    fn draw(&mut self, _: &[u8], _: usize, _: usize) -> Result<(), Box<dyn Error>> {
+        self.draws += 1;
+        Ok(())
+    }
+
+    fn toggle_fullscreen(&mut self) -> Result<(), Box<dyn Error>> {
+        self.fullscreen = !self.fullscreen;
+        Ok(())
+    }
+}
+
+#[test]
+fn redraw_while_paused() {
+    let mut runner = runner();
+    let mut video = Window::default();
+    let mut script = Script::new(&[Some(Command::TogglePause), None, None]);
+    script.0[1].redraw = true;
+    runner.run(&mut video, &mut script, &mut ()).unwrap();
+    // Pausing shows the overlay, then the window asks to be redrawn
+    assert_eq!(video.draws, 2);
+
+    let mut script = Script::new(&[Some(Command::ToggleFullscreen)]);
+    runner.run(&mut video, &mut script, &mut ()).unwrap();
+    assert!(video.fullscreen);
+    assert_eq!(video.draws, 3);
+    assert_eq!(runner.cpu().frames(), 0);
+}
+
+// Runs whole frames, recording the instructions per frame and the resets.
+#[derive(Default, Clone)]
+struct Frames {
+    ipf: Rc<Cell<usize>>,
+    resets: Rc<Cell<usize>>,
+}
+
+impl Monitor for Frames {
+    fn frame(&mut self, cpu: &mut Cpu, keys: &[bool; 16], ipf: usize) -> Result<bool, CpuError> {
+        self.ipf.set(ipf);
+        cpu.run_frame(keys, ipf)?;
+        Ok(false)
+    }
+
+    fn is_paused(&self) -> bool {
+        false
+    }
+
+    fn reset(&mut self) {
+        self.resets.set(self.resets.get() + 1);
+    }
+}
+
+#[test]
+fn monitor_follows_speed_and_reset() {
+    let mut runner = runner();
+    let frames = Frames::default();
+    runner.set_monitor(Box::new(frames.clone()));
+    let mut script = Script::new(&[None, Some(Command::SpeedUp), Some(Command::Reset)]);
+    runner.run(&mut (), &mut script, &mut ()).unwrap();
+    assert_eq!(frames.ipf.get(), 15);
+    assert_eq!(frames.resets.get(), 1);
+}
@@ -0,0 +1,77 @@
+use std::io::{self, Write};
+
+use cheap8_core::{rom_hash, Cpu};
+
+// Character of each pixel value: background, first plane,
+// second plane and both planes.
+const CHARACTERS: [char; 4] = ['.', '#', '+', '@'];
+
+/// Checksum of the visible part of the screen
+/// of `cpu`, whatever its resolution.
+pub fn checksum(cpu: &Cpu) -> u64 {
+    let display = cpu.display();
+    rom_hash(&display.get()[..display.width() * display.height()])
+}
+
+/// The screen of `cpu` as text, one line per row.
+pub fn text(cpu: &Cpu) -> String {
+    let display = cpu.display();
+    let width = display.width();
+    let mut out = String::new();
+    for row in display.get()[..width * display.height()].chunks(width) {
+        out.extend(row.iter().map(|pixel| CHARACTERS[*pixel as usize & 3]));
+        out.push('\n');
+    }
+    out
+}
+
+/// Write the screen of `cpu` to `writer` as a PNG image, one
+/// image pixel per screen pixel, with the colors of `palette`.
+pub fn png<W: Write>(cpu: &Cpu, palette: [(u8, u8, u8); 4], writer: W) -> io::Result<()> {
+    let display = cpu.display();
+    let (width, height) = (display.width(), display.height());
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    let palette: Vec<u8> = palette.iter().flat_map(|(r, g, b)| [*r, *g, *b]).collect();
+    encoder.set_palette(palette);
+    let mut writer = encoder.write_header().map_err(into_io)?;
+    writer
+        .write_image_data(&display.get()[..width * height])
+        .map_err(into_io)?;
+    writer.finish().map_err(into_io)
+}
+
+/// The registers of `cpu` as a JSON object.
+pub fn registers(cpu: &Cpu) -> String {
+    let list = |values: Vec<String>| values.join(", ");
+    format!(
+        concat!(
+            "{{\n",
+            "  \"pc\": {},\n",
+            "  \"i\": {},\n",
+            "  \"v\": [{}],\n",
+            "  \"stack\": [{}],\n",
+            "  \"delay_timer\": {},\n",
+            "  \"sound_timer\": {},\n",
+            "  \"cycles\": {},\n",
+            "  \"exited\": {}\n",
+            "}}\n"
+        ),
+        cpu.pc(),
+        cpu.index(),
+        list(cpu.registers().iter().map(u8::to_string).collect()),
+        list(cpu.stack().iter().map(u16::to_string).collect()),
+        cpu.delay_timer(),
+        cpu.sound_timer(),
+        cpu.cycles(),
+        cpu.has_exited()
+    )
+}
+
+fn into_io(e: png::EncodingError) -> io::Error {
+    match e {
+        png::EncodingError::IoError(e) => e,
+        e => io::Error::other(e),
+    }
+}
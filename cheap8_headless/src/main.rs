@@ -0,0 +1,94 @@
+use clap::Parser;
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+use cheap8_frontend::cli;
+use cheap8_frontend::repl::Session;
+use cheap8_frontend::Stop;
+
+mod dump;
+
+mod parse_args;
+use parse_args::Cli;
+
+mod script;
+use script::Script;
+
+pub fn main() -> ExitCode {
+    let mut args = Cli::parse();
+    // Reproducible runs, unless given another seed
+    args.common.seed.get_or_insert(0);
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::from(2)
+        }
+    }
+}
+
+// Run the ROM and dump the results, return false iff the
+// checksum isn't the expected one, or true right away if
+// the disassembly or the hash of the ROM was printed.
+fn run(args: &Cli) -> Result<bool, String> {
+    let mut script = match &args.input {
+        Some(path) => {
+            let src =
+                fs::read_to_string(path).map_err(|e| context("Failed to read the input", &e))?;
+            Script::parse(&src).map_err(|e| context("Invalid input", &e))?
+        }
+        None => Script::default(),
+    };
+
+    let mut runner = match cli::setup(&args.common)? {
+        Some(runner) => runner,
+        None => return Ok(true),
+    };
+    runner.set_throttle(false);
+    runner.set_max_frames(Some(args.frames));
+    runner.set_stop_on_loop(args.until_loop);
+    if args.common.debug {
+        runner.set_monitor(Box::new(Session::new(runner.ipf())));
+    }
+    let result = runner.run(&mut (), &mut script, &mut ());
+    let (stop, cpu) = cli::finish(&args.common, runner, result, "Failed to read the input")?;
+    let stop = match stop {
+        Stop::Exit => "exit".to_string(),
+        Stop::Loop { address } => format!("loop at {:#06x}", address),
+        Stop::FrameLimit | Stop::Quit => "frame limit".to_string(),
+    };
+
+    match &args.screen {
+        Some(path) if path.extension().is_some_and(|ext| ext == "png") => {
+            let file = File::create(path).map_err(|e| context("Failed to write the screen", &e))?;
+            dump::png(&cpu, args.common.palette(), BufWriter::new(file))
+                .map_err(|e| context("Failed to write the screen", &e))?;
+        }
+        Some(path) => fs::write(path, dump::text(&cpu))
+            .map_err(|e| context("Failed to write the screen", &e))?,
+        None => print!("{}", dump::text(&cpu)),
+    }
+    if let Some(path) = &args.registers {
+        fs::write(path, dump::registers(&cpu))
+            .map_err(|e| context("Failed to write the registers", &e))?;
+    }
+
+    let checksum = dump::checksum(&cpu);
+    println!("frames: {} ({})", cpu.frames(), stop);
+    println!("checksum: {:016x}", checksum);
+    match args.expect {
+        Some(expected) if expected != checksum => {
+            eprintln!("Expected the checksum {:016x}", expected);
+            Ok(false)
+        }
+        _ => Ok(true),
+    }
+}
+
+fn context(context: &str, error: &dyn Error) -> String {
+    format!("{}: {}", context, error)
+}
@@ -0,0 +1,37 @@
+use std::num::ParseIntError;
+use std::path::PathBuf;
+
+use cheap8_frontend::cli::Args;
+
+fn parse_checksum(src: &str) -> Result<u64, ParseIntError> {
+    u64::from_str_radix(src.strip_prefix("0x").unwrap_or(src), 16)
+}
+
+#[derive(clap::Parser)]
+pub struct Cli {
+    #[structopt(flatten)]
+    pub common: Args,
+
+    /// Number of frames to run, at most
+    #[structopt(short, long, default_value = "600")]
+    pub frames: u64,
+    /// Stop as soon as the program jumps to itself, which
+    /// is how most programs end
+    #[structopt(short = 'l', long)]
+    pub until_loop: bool,
+    /// Keys to press, one `FRAME KEY...` line per change, like
+    /// `120 5 6` to hold 5 and 6 from frame 120 until the next line
+    #[structopt(long, parse(from_os_str))]
+    pub input: Option<PathBuf>,
+
+    /// Write the final screen to this file, as a PNG if it ends
+    /// with .png and as text otherwise. Printed if not given
+    #[structopt(long, parse(from_os_str))]
+    pub screen: Option<PathBuf>,
+    /// Write the final registers to this file, as JSON
+    #[structopt(long, parse(from_os_str))]
+    pub registers: Option<PathBuf>,
+    /// Fail unless the checksum of the final screen is this one
+    #[structopt(long, parse(try_from_str = parse_checksum))]
+    pub expect: Option<u64>,
+}
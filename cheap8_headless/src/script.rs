@@ -0,0 +1,152 @@
+use std::error::Error;
+use std::fmt;
+
+use cheap8_frontend::{Input, InputSource};
+
+/// Keys to press during a run, parsed from lines of `FRAME KEY...`
+/// where the keys are hexadecimal digits, held from `FRAME` until the
+/// frame of the next line. Blank lines and `#` comments are ignored.
+#[derive(Default)]
+pub struct Script {
+    // Frame and keys pressed from it on, sorted by frame.
+    changes: Vec<(u64, [bool; 16])>,
+    // Next frame to poll the keys of.
+    frame: u64,
+}
+
+/// Error in line `line` of a script.
+#[derive(Debug)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ScriptError {}
+
+impl Script {
+    pub fn parse(src: &str) -> Result<Self, ScriptError> {
+        let mut changes: Vec<(u64, [bool; 16])> = Vec::new();
+        for (i, line) in src.lines().enumerate() {
+            let error = |message: String| ScriptError {
+                line: i + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or("");
+            let mut words = line.split_whitespace();
+            let frame = match words.next() {
+                Some(frame) => frame
+                    .parse()
+                    .map_err(|_| error(format!("invalid frame `{}`", frame)))?,
+                None => continue,
+            };
+            if changes.last().is_some_and(|(last, _)| *last >= frame) {
+                return Err(error("frames must be in increasing order".to_string()));
+            }
+
+            let mut keys = [false; 16];
+            for key in words {
+                match u8::from_str_radix(key, 16) {
+                    Ok(key) if key < 16 => keys[key as usize] = true,
+                    _ => return Err(error(format!("invalid key `{}`", key))),
+                }
+            }
+            changes.push((frame, keys));
+        }
+        Ok(Script { changes, frame: 0 })
+    }
+
+    /// Keys pressed during `frame`.
+    pub fn keys(&self, frame: u64) -> [bool; 16] {
+        self.changes
+            .iter()
+            .take_while(|(from, _)| *from <= frame)
+            .last()
+            .map_or([false; 16], |(_, keys)| *keys)
+    }
+}
+
+impl InputSource for Script {
+    fn poll(&mut self) -> Result<Option<Input>, Box<dyn Error>> {
+        let keys = self.keys(self.frame);
+        self.frame += 1;
+        Ok(Some(Input::from(keys)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keypad keys pressed during `frame`, as hexadecimal digits.
+    fn pressed(script: &Script, frame: u64) -> String {
+        (0..16)
+            .filter(|key| script.keys(frame)[*key])
+            .map(|key| format!("{:X}", key))
+            .collect()
+    }
+
+    fn error(src: &str) -> String {
+        Script::parse(src).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn keys_are_held_until_the_next_line() {
+        let src = "
+            # Start the game
+            10 5
+            12      # release
+
+            30 a F  # left and up
+            31 0
+        ";
+        let script = Script::parse(src).unwrap();
+        assert_eq!(pressed(&script, 0), "");
+        assert_eq!(pressed(&script, 10), "5");
+        assert_eq!(pressed(&script, 11), "5");
+        assert_eq!(pressed(&script, 12), "");
+        assert_eq!(pressed(&script, 30), "AF");
+        assert_eq!(pressed(&script, 1000), "0");
+        assert_eq!(pressed(&Script::parse("").unwrap(), 0), "");
+    }
+
+    #[test]
+    fn frames_are_polled_in_order() {
+        let mut script = Script::parse("1 1 2\n2").unwrap();
+        let mut poll = || script.poll().unwrap().unwrap().keys;
+        assert_eq!(poll(), [false; 16]);
+        assert!(poll()[1..3] == [true, true]);
+        assert_eq!(poll(), [false; 16]);
+    }
+
+    #[test]
+    fn frames_must_increase() {
+        assert_eq!(
+            error("10 1\n10 2"),
+            "line 2: frames must be in increasing order"
+        );
+        assert_eq!(
+            error("10 1\n\n# back\n5 2"),
+            "line 4: frames must be in increasing order"
+        );
+    }
+
+    #[test]
+    fn keys_must_be_hexadecimal_digits() {
+        assert_eq!(error("0 G"), "line 1: invalid key `G`");
+        assert_eq!(error("0 1\n1 10"), "line 2: invalid key `10`");
+        assert_eq!(error("0 -1"), "line 1: invalid key `-1`");
+    }
+
+    #[test]
+    fn lines_start_with_a_frame() {
+        assert_eq!(error("A 1"), "line 1: invalid frame `A`");
+        assert_eq!(error("0 1\n-5 1"), "line 2: invalid frame `-5`");
+        assert_eq!(error("1.5"), "line 1: invalid frame `1.5`");
+    }
+}
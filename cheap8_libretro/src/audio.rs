@@ -0,0 +1,64 @@
+use cheap8_core::Output;
+
+/// Sample rate of the sound sent to the frontend.
+pub const SAMPLE_RATE: usize = 44100;
+/// Stereo samples per frame, at 60 frames per second.
+pub const FRAME_SAMPLES: usize = SAMPLE_RATE / 60;
+
+const VOLUME: i16 = i16::MAX / 4;
+
+/// Generates the sound of each frame, like the buzzer of the SDL client.
+pub struct Beeper {
+    phase: f32,
+    // Whether the last frame played an XO-CHIP pattern, the phase
+    // is then the index of the bit being played.
+    pattern: bool,
+    // Interleaved left and right samples of the last frame.
+    samples: Vec<i16>,
+}
+
+impl Beeper {
+    pub fn new() -> Self {
+        Beeper {
+            phase: 0.0,
+            pattern: false,
+            samples: vec![0; FRAME_SAMPLES * 2],
+        }
+    }
+
+    /// Samples of the frame whose `output` was just produced,
+    /// silent if it doesn't beep.
+    pub fn frame(&mut self, output: &Output) -> &[i16] {
+        if !output.beep {
+            self.phase = 0.0;
+            self.samples.fill(0);
+            return &self.samples;
+        }
+
+        if output.audio_pattern.is_some() != self.pattern {
+            self.phase = 0.0;
+            self.pattern = output.audio_pattern.is_some();
+        }
+        let (phase_inc, period) = match output.audio_pattern {
+            Some(_) => {
+                let rate = 4000.0 * 2f32.powf((output.pitch as f32 - 64.0) / 48.0);
+                (rate / SAMPLE_RATE as f32, 128.0)
+            }
+            None => (440.0 / SAMPLE_RATE as f32, 1.0),
+        };
+        for sample in self.samples.chunks_mut(2) {
+            let high = match output.audio_pattern {
+                // Play the 128 bits of the pattern in loop
+                Some(pattern) => {
+                    let bit = self.phase as usize;
+                    pattern[bit / 8] & (0x80 >> (bit % 8)) != 0
+                }
+                // Generate a square wave
+                None => self.phase <= 0.5,
+            };
+            sample.fill(if high { VOLUME } else { -VOLUME });
+            self.phase = (self.phase + phase_inc) % period;
+        }
+        &self.samples
+    }
+}
@@ -0,0 +1,445 @@
+//! # cheap8_libretro
+//! A libretro core running cheap8 in RetroArch and the other
+//! libretro frontends. It exports the C functions of `libretro.h`,
+//! which the frontend calls from a single thread.
+//!
+//! The keypad is read from the keyboard, with the layout of the other
+//! clients, and from the first joypad: the directions are 2, 8, 4 and 6,
+//! A is 5, and the other buttons are the remaining keys.
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_uint, c_void};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use cheap8_core::{Cpu, HIRES_HEIGHT, HIRES_WIDTH, LORES_HEIGHT, LORES_WIDTH};
+
+mod audio;
+use audio::{Beeper, FRAME_SAMPLES, SAMPLE_RATE};
+
+pub mod ffi;
+use ffi::*;
+
+mod options;
+use options::Settings;
+
+/// Size of the buffers given to `retro_serialize`, a save state
+/// is written in it after its length as a 32 bits integer.
+pub const SERIALIZE_SIZE: usize = 0x12000;
+
+// Keyboard layout of the keypad, by key, as `RETROK` codes.
+const KEYBOARD: &[u8; 16] = b"x123qweasdzc4rfv";
+// Joypad button of each key of the keypad.
+const JOYPAD: [c_uint; 16] = [
+    RETRO_DEVICE_ID_JOYPAD_B,
+    RETRO_DEVICE_ID_JOYPAD_X,
+    RETRO_DEVICE_ID_JOYPAD_UP,
+    RETRO_DEVICE_ID_JOYPAD_Y,
+    RETRO_DEVICE_ID_JOYPAD_LEFT,
+    RETRO_DEVICE_ID_JOYPAD_A,
+    RETRO_DEVICE_ID_JOYPAD_RIGHT,
+    RETRO_DEVICE_ID_JOYPAD_L,
+    RETRO_DEVICE_ID_JOYPAD_DOWN,
+    RETRO_DEVICE_ID_JOYPAD_R,
+    RETRO_DEVICE_ID_JOYPAD_SELECT,
+    RETRO_DEVICE_ID_JOYPAD_START,
+    RETRO_DEVICE_ID_JOYPAD_L2,
+    RETRO_DEVICE_ID_JOYPAD_R2,
+    RETRO_DEVICE_ID_JOYPAD_L3,
+    RETRO_DEVICE_ID_JOYPAD_R3,
+];
+// XRGB colors of the pixels, by the planes they are set on,
+// the defaults of the SDL client.
+const COLORS: [u32; 4] = [0x000000, 0x00F0F0, 0xF0A000, 0xFFFFFF];
+
+/// Everything the core keeps between the calls of the frontend.
+struct State {
+    environment: Option<retro_environment_t>,
+    video_refresh: Option<retro_video_refresh_t>,
+    audio_sample_batch: Option<retro_audio_sample_batch_t>,
+    input_poll: Option<retro_input_poll_t>,
+    input_state: Option<retro_input_state_t>,
+    game: Option<Game>,
+}
+
+/// The game being played.
+struct Game {
+    cpu: Cpu,
+    rom: Vec<u8>,
+    settings: Settings,
+    // Set once the CPU fails, it then stays still until reset.
+    crashed: bool,
+    beeper: Beeper,
+    // XRGB pixels of the last frame.
+    frame: Vec<u32>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+    game: None,
+});
+
+fn state() -> MutexGuard<'static, State> {
+    // A panic aborts, the state can't be poisoned
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl State {
+    fn environment(&self, cmd: c_uint, data: *mut c_void) -> bool {
+        match self.environment {
+            Some(environment) => unsafe { environment(cmd, data) },
+            None => false,
+        }
+    }
+
+    // Read the core options.
+    fn read_settings(&self) -> Settings {
+        Settings::read(|key| {
+            let mut variable = retro_variable {
+                key: key.as_ptr() as *const c_char,
+                value: ptr::null(),
+            };
+            let data = &mut variable as *mut retro_variable as *mut c_void;
+            if !self.environment(RETRO_ENVIRONMENT_GET_VARIABLE, data) || variable.value.is_null() {
+                return None;
+            }
+            let value = unsafe { CStr::from_ptr(variable.value) };
+            Some(value.to_string_lossy().into_owned())
+        })
+    }
+
+    // Show `message` on screen for a few seconds.
+    fn message(&self, message: &str) {
+        let message = message.replace('\0', "");
+        let message = format!("{}\0", message);
+        let mut message = retro_message {
+            msg: message.as_ptr() as *const c_char,
+            frames: 180,
+        };
+        let data = &mut message as *mut retro_message as *mut c_void;
+        self.environment(RETRO_ENVIRONMENT_SET_MESSAGE, data);
+    }
+
+    // Keypad state, from the keyboard and the first joypad.
+    fn keys(&self) -> [bool; 16] {
+        let mut keys = [false; 16];
+        let input_state = match self.input_state {
+            Some(input_state) => input_state,
+            None => return keys,
+        };
+        for (key, pressed) in keys.iter_mut().enumerate() {
+            *pressed = unsafe {
+                input_state(0, RETRO_DEVICE_KEYBOARD, 0, KEYBOARD[key] as c_uint) != 0
+                    || input_state(0, RETRO_DEVICE_JOYPAD, 0, JOYPAD[key]) != 0
+            };
+        }
+        keys
+    }
+
+    // Set `cpu` up with the core options, and load the ROM in it.
+    fn start(&self, mut cpu: Cpu, rom: Vec<u8>) -> Result<Game, String> {
+        let settings = self.read_settings();
+        cpu.set_load_address(settings.load_address);
+        cpu.reset();
+        cpu.load_bytes(&rom)
+            .map_err(|e| format!("Failed to load the ROM: {}", e))?;
+        Ok(Game {
+            cpu,
+            rom,
+            settings,
+            crashed: false,
+            beeper: Beeper::new(),
+            frame: vec![0; HIRES_WIDTH * HIRES_HEIGHT],
+        })
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// Declare the core options to the frontend.
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: retro_environment_t) {
+    let mut state = state();
+    state.environment = Some(environment);
+    let mut variables = options::variables();
+    state.environment(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_mut_ptr() as *mut c_void,
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: retro_video_refresh_t) {
+    state().video_refresh = Some(video_refresh);
+}
+
+/// Unused, the sound is sent in batches.
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_: retro_audio_sample_t) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: retro_audio_sample_batch_t) {
+    state().audio_sample_batch = Some(audio_sample_batch);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: retro_input_poll_t) {
+    state().input_poll = Some(input_poll);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: retro_input_state_t) {
+    state().input_state = Some(input_state);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    state().game = None;
+}
+
+/// # Safety
+///
+/// `info` must point to a `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut retro_system_info) {
+    *info = retro_system_info {
+        library_name: b"cheap8\0".as_ptr() as *const c_char,
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: b"ch8|c8|sc8|xo8\0".as_ptr() as *const c_char,
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut retro_system_av_info) {
+    *info = retro_system_av_info {
+        geometry: retro_game_geometry {
+            base_width: LORES_WIDTH as c_uint,
+            base_height: LORES_HEIGHT as c_uint,
+            max_width: HIRES_WIDTH as c_uint,
+            max_height: HIRES_HEIGHT as c_uint,
+            aspect_ratio: 2.0,
+        },
+        timing: retro_system_timing {
+            fps: 60.0,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+/// Every device is read the same way.
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_: c_uint, _: c_uint) {}
+
+/// Restart the game, with the quirks and load address
+/// currently chosen in the core options.
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    let mut state = state();
+    if let Some(game) = state.game.take() {
+        // Keep the seed, so that resetting replays the same game
+        let quirks = state.read_settings().quirks;
+        let cpu = Cpu::with_seed(quirks, game.cpu.seed());
+        match state.start(cpu, game.rom) {
+            Ok(game) => state.game = Some(game),
+            Err(message) => state.message(&message),
+        }
+    }
+}
+
+/// Run one frame, and send its screen and sound to the frontend.
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let mut state = state();
+    let mut updated = false;
+    state.environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        &mut updated as *mut bool as *mut c_void,
+    );
+    let settings = updated.then(|| state.read_settings());
+    if let Some(input_poll) = state.input_poll {
+        unsafe { input_poll() };
+    }
+    let keys = state.keys();
+
+    let video_refresh = state.video_refresh;
+    let audio_sample_batch = state.audio_sample_batch;
+    let game = match &mut state.game {
+        Some(game) => game,
+        None => return,
+    };
+    if let Some(settings) = settings {
+        game.settings = settings;
+    }
+    let mut error = None;
+    if !game.crashed {
+        if let Err(e) = game.cpu.run_frame(&keys, game.settings.ipf) {
+            game.crashed = true;
+            error = Some(format!("The emulator stopped: {}", e));
+        }
+    }
+
+    let output = game.cpu.output();
+    for (color, pixel) in game.frame.iter_mut().zip(output.screen) {
+        *color = COLORS[*pixel as usize & 3];
+    }
+    if let Some(video_refresh) = video_refresh {
+        unsafe {
+            video_refresh(
+                game.frame.as_ptr() as *const c_void,
+                output.width as c_uint,
+                output.height as c_uint,
+                output.width * 4,
+            )
+        };
+    }
+    let samples = game.beeper.frame(&output);
+    if let Some(audio_sample_batch) = audio_sample_batch {
+        unsafe { audio_sample_batch(samples.as_ptr(), FRAME_SAMPLES) };
+    }
+
+    if let Some(error) = error {
+        state.message(&error);
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SERIALIZE_SIZE
+}
+
+/// Write a save state in the `size` bytes at `data`.
+///
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let state = state();
+    let game = match &state.game {
+        Some(game) => game,
+        None => return false,
+    };
+    let bytes = game.cpu.save_state();
+    if data.is_null() || size < SERIALIZE_SIZE || bytes.len() + 4 > SERIALIZE_SIZE {
+        return false;
+    }
+    let out = std::slice::from_raw_parts_mut(data as *mut u8, size);
+    out[..4].copy_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out[4..4 + bytes.len()].copy_from_slice(&bytes);
+    out[4 + bytes.len()..].fill(0);
+    true
+}
+
+/// Restore a save state written by [`retro_serialize`].
+///
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut state = state();
+    let game = match &mut state.game {
+        Some(game) => game,
+        None => return false,
+    };
+    if data.is_null() || size < 4 {
+        return false;
+    }
+    let bytes = std::slice::from_raw_parts(data as *const u8, size);
+    let len = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let state = match bytes[4..].get(..len) {
+        Some(state) => state,
+        None => return false,
+    };
+    match game.cpu.load_state(state) {
+        Ok(()) => {
+            game.crashed = false;
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Cheats aren't supported.
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+/// Cheats aren't supported.
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_: c_uint, _: bool, _: *const c_char) {}
+
+/// Start the game whose ROM is given in `info`.
+///
+/// # Safety
+///
+/// `info` must be null or point to a `retro_game_info` whose
+/// `data` points to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(info: *const retro_game_info) -> bool {
+    let mut state = state();
+    if info.is_null() || (*info).data.is_null() {
+        return false;
+    }
+    let rom = std::slice::from_raw_parts((*info).data as *const u8, (*info).size).to_vec();
+
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !state.environment(
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        &mut format as *mut c_uint as *mut c_void,
+    ) {
+        return false;
+    }
+    let cpu = Cpu::new(state.read_settings().quirks);
+    match state.start(cpu, rom) {
+        Ok(game) => {
+            state.game = Some(game);
+            true
+        }
+        Err(message) => {
+            state.message(&message);
+            false
+        }
+    }
+}
+
+/// There are no special games.
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_: c_uint, _: *const retro_game_info, _: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    state().game = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// The memory isn't exposed.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+/// The memory isn't exposed.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_: c_uint) -> usize {
+    0
+}
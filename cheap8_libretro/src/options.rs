@@ -0,0 +1,129 @@
+//! Core options, the settings RetroArch shows in its menu.
+use std::os::raw::c_char;
+use std::ptr;
+
+use cheap8_core::{Quirks, DEFAULT_LOAD_ADDRESS};
+use cheap8_frontend::parse::{parse_address, parse_quirks};
+
+use crate::ffi::retro_variable;
+
+const PROFILE: &[u8] = b"cheap8_profile\0";
+const IPF: &[u8] = b"cheap8_ipf\0";
+const LOAD_ADDRESS: &[u8] = b"cheap8_load_address\0";
+// Options overriding a quirk of the profile, in the
+// order of the fields of `Quirks`.
+const QUIRKS: [&[u8]; 6] = [
+    b"cheap8_shift_vy\0",
+    b"cheap8_load_store_increment\0",
+    b"cheap8_jump_vx\0",
+    b"cheap8_vf_reset\0",
+    b"cheap8_clip_sprites\0",
+    b"cheap8_display_wait\0",
+];
+
+// Description and values of each option, the first value being the default.
+const VARIABLES: [(&[u8], &[u8]); 9] = [
+    (
+        PROFILE,
+        b"Quirks profile (restart); modern|vip|chip48|schip\0",
+    ),
+    (
+        QUIRKS[0],
+        b"8XY6 and 8XYE shift VY (restart); profile|enabled|disabled\0",
+    ),
+    (
+        QUIRKS[1],
+        b"FX55 and FX65 increment I (restart); profile|enabled|disabled\0",
+    ),
+    (
+        QUIRKS[2],
+        b"BNNN jumps to NNN + VX (restart); profile|enabled|disabled\0",
+    ),
+    (
+        QUIRKS[3],
+        b"8XY1, 8XY2 and 8XY3 reset VF (restart); profile|enabled|disabled\0",
+    ),
+    (
+        QUIRKS[4],
+        b"Clip sprites at the edges (restart); profile|enabled|disabled\0",
+    ),
+    (
+        QUIRKS[5],
+        b"DXYN waits for the vertical blank (restart); profile|enabled|disabled\0",
+    ),
+    (
+        IPF,
+        b"Instructions per frame; 15|7|10|20|30|50|100|200|500|1000\0",
+    ),
+    (LOAD_ADDRESS, b"Load address (restart); 0x200|0x600\0"),
+];
+
+/// The options to declare with `RETRO_ENVIRONMENT_SET_VARIABLES`,
+/// ending with a null one.
+pub fn variables() -> Vec<retro_variable> {
+    VARIABLES
+        .iter()
+        .map(|(key, value)| retro_variable {
+            key: key.as_ptr() as *const c_char,
+            value: value.as_ptr() as *const c_char,
+        })
+        .chain(Some(retro_variable {
+            key: ptr::null(),
+            value: ptr::null(),
+        }))
+        .collect()
+}
+
+/// Settings chosen in the core options. The quirks and load
+/// address only apply once the game is reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    pub quirks: Quirks,
+    pub ipf: usize,
+    pub load_address: u16,
+}
+
+impl Settings {
+    /// Read the settings with `get`, which gives the value of an
+    /// option from its nul terminated key. Options that are
+    /// missing or invalid keep their default.
+    pub fn read<F: FnMut(&[u8]) -> Option<String>>(mut get: F) -> Self {
+        let mut settings = Settings::default();
+        if let Some(quirks) = get(PROFILE).and_then(|value| parse_quirks(&value).ok()) {
+            settings.quirks = quirks;
+        }
+        let quirks = &mut settings.quirks;
+        let overrides = [
+            &mut quirks.shift_vy,
+            &mut quirks.load_store_increment,
+            &mut quirks.jump_vx,
+            &mut quirks.vf_reset,
+            &mut quirks.clip_sprites,
+            &mut quirks.display_wait,
+        ];
+        for (key, quirk) in QUIRKS.iter().zip(overrides) {
+            match get(key).as_deref() {
+                Some("enabled") => *quirk = true,
+                Some("disabled") => *quirk = false,
+                _ => (),
+            }
+        }
+        if let Some(ipf) = get(IPF).and_then(|value| value.parse().ok()) {
+            settings.ipf = ipf;
+        }
+        if let Some(address) = get(LOAD_ADDRESS).and_then(|value| parse_address(&value).ok()) {
+            settings.load_address = address;
+        }
+        settings
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            quirks: Quirks::modern(),
+            ipf: 15,
+            load_address: DEFAULT_LOAD_ADDRESS,
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Drives the exported libretro functions as a frontend would, with
+//! callbacks recording what the core sends.
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_uint, c_void};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use cheap8_libretro::ffi::*;
+use cheap8_libretro::*;
+
+/// What the core sent to the frontend, and the options and inputs it reads.
+#[derive(Default)]
+struct Frontend {
+    variables: Vec<String>,
+    options: Vec<(String, CString)>,
+    messages: Vec<String>,
+    // Width, height and pixels of the last frame.
+    video: Option<(usize, usize, Vec<u32>)>,
+    audio_frames: usize,
+    buttons: Vec<c_uint>,
+}
+
+static FRONTEND: Mutex<Option<Frontend>> = Mutex::new(None);
+// The core is global, run one test at a time.
+static TEST: Mutex<()> = Mutex::new(());
+
+fn frontend<T>(f: impl FnOnce(&mut Frontend) -> T) -> T {
+    let mut frontend = FRONTEND.lock().unwrap_or_else(PoisonError::into_inner);
+    f(frontend.get_or_insert_with(Frontend::default))
+}
+
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match cmd {
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => {
+            *(data as *const c_uint) == RETRO_PIXEL_FORMAT_XRGB8888
+        }
+        RETRO_ENVIRONMENT_SET_VARIABLES => {
+            let mut variable = data as *const retro_variable;
+            while !(*variable).key.is_null() {
+                let key = CStr::from_ptr((*variable).key).to_str().unwrap();
+                let value = CStr::from_ptr((*variable).value).to_str().unwrap();
+                frontend(|f| f.variables.push(format!("{}={}", key, value)));
+                variable = variable.add(1);
+            }
+            true
+        }
+        RETRO_ENVIRONMENT_GET_VARIABLE => {
+            let variable = data as *mut retro_variable;
+            let key = CStr::from_ptr((*variable).key).to_str().unwrap();
+            frontend(|f| match f.options.iter().find(|(k, _)| k == key) {
+                Some((_, value)) => {
+                    (*variable).value = value.as_ptr();
+                    true
+                }
+                None => false,
+            })
+        }
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE => {
+            *(data as *mut bool) = false;
+            true
+        }
+        RETRO_ENVIRONMENT_SET_MESSAGE => {
+            let message = CStr::from_ptr((*(data as *const retro_message)).msg);
+            let message = message.to_str().unwrap().to_string();
+            frontend(|f| f.messages.push(message));
+            true
+        }
+        _ => false,
+    }
+}
+
+unsafe extern "C" fn video_refresh(
+    data: *const c_void,
+    width: c_uint,
+    height: c_uint,
+    pitch: usize,
+) {
+    let (width, height) = (width as usize, height as usize);
+    assert_eq!(pitch, width * 4);
+    let pixels = std::slice::from_raw_parts(data as *const u32, width * height).to_vec();
+    frontend(|f| f.video = Some((width, height, pixels)));
+}
+
+unsafe extern "C" fn audio_sample_batch(_: *const i16, frames: usize) -> usize {
+    frontend(|f| f.audio_frames += frames);
+    frames
+}
+
+unsafe extern "C" fn input_poll() {}
+
+unsafe extern "C" fn input_state(port: c_uint, device: c_uint, _: c_uint, id: c_uint) -> i16 {
+    let pressed = port == 0 && device == RETRO_DEVICE_JOYPAD;
+    frontend(|f| pressed && f.buttons.contains(&id)) as i16
+}
+
+// Start the core with `options`, and load `rom`.
+fn start(options: &[(&str, &str)], rom: &[u8]) -> MutexGuard<'static, ()> {
+    let test = TEST.lock().unwrap_or_else(PoisonError::into_inner);
+    frontend(|f| {
+        *f = Frontend::default();
+        f.options = options
+            .iter()
+            .map(|(key, value)| (key.to_string(), CString::new(*value).unwrap()))
+            .collect();
+    });
+    assert_eq!(retro_api_version(), RETRO_API_VERSION);
+    retro_set_environment(environment);
+    retro_set_video_refresh(video_refresh);
+    retro_set_audio_sample_batch(audio_sample_batch);
+    retro_set_input_poll(input_poll);
+    retro_set_input_state(input_state);
+    retro_init();
+    let info = retro_game_info {
+        path: ptr::null(),
+        data: rom.as_ptr() as *const c_void,
+        size: rom.len(),
+        meta: ptr::null(),
+    };
+    assert!(unsafe { retro_load_game(&info) });
+    test
+}
+
+// Pixels of the last frame set on the first plane.
+fn lit_pixels() -> Vec<(usize, usize)> {
+    frontend(|f| {
+        let (width, _, pixels) = f.video.as_ref().unwrap();
+        pixels
+            .iter()
+            .enumerate()
+            .filter(|(_, color)| **color == 0x00F0F0)
+            .map(|(i, _)| (i % width, i / width))
+            .collect()
+    })
+}
+
+// Wait for key 5, then draw the 5 of the font at (0, 5) and loop.
+const WAIT_KEY: &[u8] = &[
+    0x61, 0x05, 0xE1, 0x9E, 0x12, 0x02, 0xF1, 0x29, 0xD0, 0x15, 0x12, 0x0A,
+];
+
+#[test]
+fn runs_frames() {
+    let _test = start(&[], WAIT_KEY);
+    retro_run();
+    frontend(|f| {
+        let (width, height, _) = f.video.as_ref().unwrap();
+        assert_eq!((*width, *height), (64, 32));
+        assert_eq!(f.audio_frames, 735);
+        assert!(f.variables.contains(
+            &"cheap8_ipf=Instructions per frame; 15|7|10|20|30|50|100|200|500|1000".to_string()
+        ));
+    });
+    assert!(lit_pixels().is_empty());
+
+    frontend(|f| f.buttons.push(RETRO_DEVICE_ID_JOYPAD_A));
+    retro_run();
+    assert_eq!(lit_pixels()[..5], [(0, 5), (1, 5), (2, 5), (3, 5), (0, 6)]);
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[test]
+fn options_are_read() {
+    let _test = start(&[("cheap8_ipf", "1")], WAIT_KEY);
+    frontend(|f| f.buttons.push(RETRO_DEVICE_ID_JOYPAD_A));
+    // Drawing is the fourth instruction
+    for _ in 0..3 {
+        retro_run();
+        assert!(lit_pixels().is_empty());
+    }
+    retro_run();
+    assert!(!lit_pixels().is_empty());
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[test]
+fn states_are_restored() {
+    let _test = start(&[], WAIT_KEY);
+    retro_run();
+    let mut state = vec![0xAAu8; retro_serialize_size()];
+    assert_eq!(state.len(), SERIALIZE_SIZE);
+    assert!(unsafe { retro_serialize(state.as_mut_ptr() as *mut c_void, state.len()) });
+
+    frontend(|f| f.buttons.push(RETRO_DEVICE_ID_JOYPAD_A));
+    retro_run();
+    assert!(!lit_pixels().is_empty());
+
+    assert!(unsafe { retro_unserialize(state.as_ptr() as *const c_void, state.len()) });
+    frontend(|f| f.buttons.clear());
+    retro_run();
+    assert!(lit_pixels().is_empty());
+    assert!(!unsafe { retro_unserialize(state.as_ptr() as *const c_void, 3) });
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[test]
+fn errors_are_shown() {
+    let _test = start(&[], &[0xFF, 0xFF]);
+    retro_run();
+    retro_run();
+    frontend(|f| {
+        assert_eq!(
+            f.messages,
+            ["The emulator stopped: unknown opcode 0xffff at 0x200"]
+        );
+    });
+    retro_reset();
+    retro_run();
+    frontend(|f| assert_eq!(f.messages.len(), 2));
+    retro_unload_game();
+    retro_deinit();
+}
+
+#[test]
+fn system_info() {
+    let mut info = retro_system_av_info {
+        geometry: retro_game_geometry {
+            base_width: 0,
+            base_height: 0,
+            max_width: 0,
+            max_height: 0,
+            aspect_ratio: 0.0,
+        },
+        timing: retro_system_timing {
+            fps: 0.0,
+            sample_rate: 0.0,
+        },
+    };
+    unsafe { retro_get_system_av_info(&mut info) };
+    assert_eq!(
+        (info.geometry.max_width, info.geometry.max_height),
+        (128, 64)
+    );
+    assert_eq!(info.timing.sample_rate / info.timing.fps, 735.0);
+
+    let mut info = retro_system_info {
+        library_name: ptr::null(),
+        library_version: ptr::null(),
+        valid_extensions: ptr::null(),
+        need_fullpath: true,
+        block_extract: true,
+    };
+    unsafe { retro_get_system_info(&mut info) };
+    let name = unsafe { CStr::from_ptr(info.library_name as *const c_char) };
+    assert_eq!(name.to_str(), Ok("cheap8"));
+    assert!(!info.need_fullpath);
+}
@@ -4,10 +4,12 @@ use sdl2::audio::AudioCallback;
 use sdl2::audio::AudioDevice;
 use sdl2::audio::AudioSpecDesired;
 
+use cheap8_frontend::AudioSink;
+
 /// Struct that drives audio, taken
 /// from sdl2 examples.
 pub struct AudioDriver {
-    device: AudioDevice<SquareWave>,
+    device: AudioDevice<Buzzer>,
 }
 
 impl AudioDriver {
@@ -23,41 +25,83 @@ impl AudioDriver {
 
         Ok(AudioDriver {
             device: audio_substystem
-                .open_playback(None, &desired_spec, |spec| SquareWave {
+                .open_playback(None, &desired_spec, |spec| Buzzer {
+                    freq: spec.freq as f32,
                     phase_inc: 440.0 / spec.freq as f32,
                     phase: 0.0,
                     volume: 0.25,
+                    pattern: None,
                 })?,
         })
     }
+}
 
-    pub fn play(&self) {
+impl AudioSink for AudioDriver {
+    /// Play the XO-CHIP `pattern` at `pitch`, or a
+    /// square wave if there is no pattern.
+    fn play(&mut self, pattern: Option<&[u8; 16]>, pitch: u8) -> Result<(), Box<dyn Error>> {
+        {
+            let mut buzzer = self.device.lock();
+            match pattern {
+                Some(pattern) => {
+                    if buzzer.pattern.is_none() {
+                        buzzer.phase = 0.0;
+                    }
+                    let rate = 4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0);
+                    buzzer.phase_inc = rate / buzzer.freq;
+                    buzzer.pattern = Some(*pattern);
+                }
+                None => {
+                    buzzer.phase_inc = 440.0 / buzzer.freq;
+                    buzzer.pattern = None;
+                }
+            }
+        }
         self.device.resume();
+        Ok(())
     }
 
-    pub fn stop(&self) {
+    fn stop(&mut self) {
         self.device.pause();
     }
 }
 
-struct SquareWave {
+struct Buzzer {
+    freq: f32,
     phase_inc: f32,
     phase: f32,
     volume: f32,
+    // XO-CHIP pattern, the phase is then the
+    // index of the bit being played.
+    pattern: Option<[u8; 16]>,
 }
 
-impl AudioCallback for SquareWave {
+impl AudioCallback for Buzzer {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
         for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+            match self.pattern {
+                // Play the 128 bits of the pattern in loop
+                Some(pattern) => {
+                    let bit = self.phase as usize;
+                    *x = if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                        self.volume
+                    } else {
+                        -self.volume
+                    };
+                    self.phase = (self.phase + self.phase_inc) % 128.0;
+                }
+                // Generate a square wave
+                None => {
+                    *x = if self.phase <= 0.5 {
+                        self.volume
+                    } else {
+                        -self.volume
+                    };
+                    self.phase = (self.phase + self.phase_inc) % 1.0;
+                }
+            }
         }
     }
 }
@@ -1,69 +1,159 @@
 use std::error::Error;
 
-use sdl2;
+use sdl2::gfx::primitives::DrawRenderer;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::video::{FullscreenType, Window};
 
-use cheap8_core::{HEIGHT, WIDTH};
+use cheap8_core::{LORES_HEIGHT, LORES_WIDTH};
+use cheap8_frontend::VideoSink;
 
 use crate::parse_args::Cli;
 
+/// How the screen is scaled to the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    /// By a whole number of window pixels per screen pixel, so they
+    /// all have the same size.
+    Integer,
+    /// As large as fits in the window, keeping the aspect ratio.
+    Fit,
+}
+
 /// Struct that can display chip8 screen to SDL window
 pub struct DisplayDriver {
-    scale_factor: u32,
-    pixel_color: Color,
-    bg_color: Color,
+    // Color of each pixel value: background, first plane,
+    // second plane and both planes.
+    palette: [Color; 4],
     canvas: Canvas<Window>,
+    scaling: Scaling,
+    // Lines of text shown in the top left corner.
+    overlay: Option<String>,
 }
 
 impl DisplayDriver {
-    /// Create new driver from [`sdl2::Sdl`]. `args` are used to
-    /// know the `scale_factor` of the window and pixels colors.
-    pub fn new(sdl_context: &sdl2::Sdl, args: &Cli) -> Result<Self,Box<dyn Error>> {
+    /// Create new driver from [`sdl2::Sdl`]. `args` are used to know
+    /// the `scale_factor` of the resizable window, whether it starts in
+    /// fullscreen, the `scaling` of the screen and pixels colors.
+    pub fn new(sdl_context: &sdl2::Sdl, args: &Cli) -> Result<Self, Box<dyn Error>> {
         let video_subsystem = sdl_context.video()?;
-        let window = video_subsystem
-            .window(
-                "Cheap8",
-                (WIDTH as u32) * args.scale_factor,
-                (HEIGHT as u32) * args.scale_factor,
-            )
-            .position_centered()
-            .build()?;
+        let mut window = video_subsystem.window(
+            "Cheap8",
+            (LORES_WIDTH as u32) * args.scale_factor,
+            (LORES_HEIGHT as u32) * args.scale_factor,
+        );
+        window.position_centered().resizable();
+        if args.fullscreen {
+            window.fullscreen_desktop();
+        }
+        let window = window.build()?;
 
         let canvas = window.into_canvas().build()?;
-        let (pr, pg, pb) = Cli::rgb_color(args.pixel_color);
-        let (br, bg, bb) = Cli::rgb_color(args.bg_color);
+        let palette = args.common.palette().map(|(r, g, b)| Color::RGB(r, g, b));
 
         Ok(DisplayDriver {
-            scale_factor: args.scale_factor,
-            pixel_color: Color::RGB(pr, pg, pb),
-            bg_color: Color::RGB(br, bg, bb),
+            palette,
             canvas,
+            scaling: args.scaling,
+            overlay: None,
         })
     }
 
-    /// Draw `image` to the screen.
-    pub fn draw(&mut self, image: &[bool; WIDTH * HEIGHT]) -> () {
-        self.canvas.set_draw_color(self.bg_color);
+    // Area of the window where the `width` x `height` screen is drawn,
+    // centered, the rest being letterboxed.
+    fn viewport(&self, width: u32, height: u32) -> Result<Rect, Box<dyn Error>> {
+        let (window_width, window_height) = self.canvas.output_size()?;
+        let (viewport_width, viewport_height) = match self.scaling {
+            Scaling::Integer => {
+                let scale = (window_width / width).min(window_height / height).max(1);
+                (width * scale, height * scale)
+            }
+            // Compare the aspect ratios in integers to avoid rounding errors
+            Scaling::Fit if window_width * height > window_height * width => {
+                (window_height * width / height, window_height)
+            }
+            Scaling::Fit => (window_width, window_width * height / width),
+        };
+        Ok(Rect::new(
+            (window_width as i32 - viewport_width as i32) / 2,
+            (window_height as i32 - viewport_height as i32) / 2,
+            viewport_width.max(1),
+            viewport_height.max(1),
+        ))
+    }
+
+    // Draw the lines of the overlay with the 8x8 font of SDL_gfx,
+    // scaled with the window, over boxes of the background color.
+    fn draw_overlay(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        let (window_width, _) = self.canvas.output_size()?;
+        let scale = (window_width / 256).max(1) as f32;
+        self.canvas.set_scale(scale, scale)?;
+        for (i, line) in text.lines().enumerate() {
+            let y = 2 + 10 * i as i32;
+            self.canvas.set_draw_color(self.palette[0]);
+            self.canvas
+                .fill_rect(Rect::new(1, y - 1, 8 * line.len() as u32 + 2, 10))?;
+            self.canvas.string(2, y as i16, line, self.palette[1])?;
+        }
+        self.canvas.set_scale(1.0, 1.0)?;
+        Ok(())
+    }
+}
+
+impl VideoSink for DisplayDriver {
+    /// Draw `image` of size `width` x `height` to the screen, scaled
+    /// to the current size of the window with black bars around it.
+    fn draw(&mut self, image: &[u8], width: usize, height: usize) -> Result<(), Box<dyn Error>> {
+        let viewport = self.viewport(width as u32, height as u32)?;
+        // Edges of the pixels at index `i` of `count`, in window pixels,
+        // so pixels of a fractional size leave no gaps between them
+        let edge = |i: usize, count: usize, size: u32| (i as u32 * size / count as u32) as i32;
+
+        self.canvas.set_draw_color(Color::BLACK);
         self.canvas.clear();
-        self.canvas.set_draw_color(self.pixel_color);
-        for x in 0..WIDTH {
-            for y in 0..HEIGHT {
-                if image[x + y * WIDTH] {
-                    self.canvas
-                        .fill_rect(Rect::new(
-                            x as i32 * self.scale_factor as i32,
-                            y as i32 * self.scale_factor as i32,
-                            self.scale_factor,
-                            self.scale_factor,
-                        ))
-                        .unwrap();
+        self.canvas.set_draw_color(self.palette[0]);
+        self.canvas.fill_rect(viewport)?;
+        for x in 0..width {
+            let left = viewport.x() + edge(x, width, viewport.width());
+            let right = viewport.x() + edge(x + 1, width, viewport.width());
+            for y in 0..height {
+                let pixel = image[x + y * width] as usize;
+                if pixel != 0 {
+                    let top = viewport.y() + edge(y, height, viewport.height());
+                    let bottom = viewport.y() + edge(y + 1, height, viewport.height());
+                    self.canvas.set_draw_color(self.palette[pixel & 0b11]);
+                    self.canvas.fill_rect(Rect::new(
+                        left,
+                        top,
+                        (right - left) as u32,
+                        (bottom - top) as u32,
+                    ))?;
                 }
             }
         }
 
+        if let Some(text) = self.overlay.take() {
+            let result = self.draw_overlay(&text);
+            self.overlay = Some(text);
+            result?;
+        }
+
         self.canvas.present();
+        Ok(())
+    }
+
+    fn set_overlay(&mut self, text: Option<&str>) {
+        self.overlay = text.map(str::to_string);
+    }
+
+    fn toggle_fullscreen(&mut self) -> Result<(), Box<dyn Error>> {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        window.set_fullscreen(fullscreen)?;
+        Ok(())
     }
 }
@@ -1,68 +1,138 @@
+use std::collections::VecDeque;
 use std::error::Error;
 
-use sdl2;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::GameControllerSubsystem;
+
+use cheap8_frontend::{Command, Input, InputSource};
+
+use crate::keymap::Keymap;
 
 /// Struct that deals with inputs events
 pub struct InputDriver {
     event_pump: sdl2::EventPump,
+    game_controller: GameControllerSubsystem,
+    // Game controllers plugged in, opened as they are.
+    controllers: Vec<GameController>,
+    keymap: Keymap,
+    // Hotkeys pressed, given one per frame.
+    commands: VecDeque<Command>,
+    // Whether the window changed since the last frame.
+    redraw: bool,
 }
 
 impl InputDriver {
     /// Create a new driver from [`sdl2::Sdl`].
     pub fn new(sdl_context: &sdl2::Sdl) -> Result<Self,Box<dyn Error>> {
         let event_pump = sdl_context.event_pump()?;
-        Ok(InputDriver { event_pump })
+        // The controllers already plugged in are added by events too
+        let game_controller = sdl_context.game_controller()?;
+        Ok(InputDriver {
+            event_pump,
+            game_controller,
+            controllers: Vec::new(),
+            keymap: Keymap::default(),
+            commands: VecDeque::new(),
+            redraw: false,
+        })
+    }
+
+    /// Read the keypad with `keymap`, instead of the
+    /// 1234/QWER/ASDF/ZXCV layout and default buttons.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    // Command of the hotkey `keycode`.
+    fn hotkey(keycode: Keycode) -> Option<Command> {
+        match keycode {
+            Keycode::F1 => Some(Command::TogglePause),
+            Keycode::F2 => Some(Command::Step),
+            Keycode::F3 => Some(Command::Reset),
+            Keycode::Minus | Keycode::KpMinus => Some(Command::SpeedDown),
+            Keycode::Equals | Keycode::KpPlus => Some(Command::SpeedUp),
+            Keycode::F11 => Some(Command::ToggleFullscreen),
+            _ => None,
+        }
     }
+}
 
-    /// Return `Some(key_pressed)` if it reads keypad inputs,
-    /// where `true` mean pressed.
-    /// Return None if recieves `[Event::Quit]` or `[Keycode::Escape]`.
-    pub fn poll(&mut self) -> Option<[bool; 16]> {
+impl InputSource for InputDriver {
+    /// Return the keypad inputs from the keyboard and the game
+    /// controllers, where `true` mean pressed, whether the rewind
+    /// key (backspace) is held, and the next hotkey pressed: F1
+    /// (or Start) to pause, F2 to run a frame while paused, F3 to
+    /// reset, - and = to change the speed and F11 to toggle fullscreen.
+    /// Ask to redraw the screen when the window is resized or exposed.
+    /// Return `None` if it recieves [`Event::Quit`] or [`Keycode::Escape`].
+    fn poll(&mut self) -> Result<Option<Input>, Box<dyn Error>> {
         for event in self.event_pump.poll_iter() {
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
-                } => return None,
+                } => return Ok(None),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => self.commands.extend(Self::hotkey(keycode)),
+                Event::ControllerButtonDown {
+                    button: Button::Start,
+                    ..
+                } => self.commands.push_back(Command::TogglePause),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                    ..
+                } => self.redraw = true,
+                Event::ControllerDeviceAdded { which, .. } => {
+                    // Ignore the controllers SDL can't open
+                    if let Ok(controller) = self.game_controller.open(which) {
+                        self.controllers.push(controller);
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => self
+                    .controllers
+                    .retain(|controller| controller.instance_id() != which),
                 _ => (),
             }
         }
 
+        let keyboard = self.event_pump.keyboard_state();
         let mut key_pressed = [false; 16];
-
-        self.event_pump
-            .keyboard_state()
+        keyboard
             .pressed_scancodes()
-            .filter_map(Keycode::from_scancode)
-            .filter_map(Self::key_code_to_hex)
+            .flat_map(|scancode| self.keymap.keys(scancode))
             .for_each(|hex_key| key_pressed[hex_key] = true);
+        let threshold = self.keymap.stick_threshold();
+        for controller in &self.controllers {
+            for (button, hex_key) in self.keymap.buttons() {
+                if controller.button(*button) || stick_pushed(controller, *button, threshold) {
+                    key_pressed[*hex_key] = true;
+                }
+            }
+        }
 
-        Some(key_pressed)
+        Ok(Some(Input {
+            keys: key_pressed,
+            rewind: keyboard.is_scancode_pressed(Scancode::Backspace),
+            command: self.commands.pop_front(),
+            redraw: std::mem::take(&mut self.redraw),
+        }))
     }
+}
 
-    // Chip8 keymap
-    fn key_code_to_hex(keycode: Keycode) -> Option<usize> {
-        match keycode {
-            Keycode::Num1 => Some(0x1),
-            Keycode::Num2 => Some(0x2),
-            Keycode::Num3 => Some(0x3),
-            Keycode::Num4 => Some(0xc),
-            Keycode::Q => Some(0x4),
-            Keycode::W => Some(0x5),
-            Keycode::E => Some(0x6),
-            Keycode::R => Some(0xd),
-            Keycode::A => Some(0x7),
-            Keycode::S => Some(0x8),
-            Keycode::D => Some(0x9),
-            Keycode::F => Some(0xe),
-            Keycode::Z => Some(0xa),
-            Keycode::X => Some(0x0),
-            Keycode::C => Some(0xb),
-            Keycode::V => Some(0xf),
-            _ => None,
-        }
+// True iff `button` is a direction of the d-pad and the
+// left stick of `controller` is pushed past `threshold` in it.
+fn stick_pushed(controller: &GameController, button: Button, threshold: i16) -> bool {
+    match button {
+        Button::DPadUp => controller.axis(Axis::LeftY) <= -threshold,
+        Button::DPadDown => controller.axis(Axis::LeftY) >= threshold,
+        Button::DPadLeft => controller.axis(Axis::LeftX) <= -threshold,
+        Button::DPadRight => controller.axis(Axis::LeftX) >= threshold,
+        _ => false,
     }
 }
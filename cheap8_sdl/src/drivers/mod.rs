@@ -3,5 +3,5 @@ mod display_driver;
 mod input_driver;
 
 pub use audio_driver::AudioDriver;
-pub use display_driver::DisplayDriver;
+pub use display_driver::{DisplayDriver, Scaling};
 pub use input_driver::InputDriver;
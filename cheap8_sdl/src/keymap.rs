@@ -0,0 +1,392 @@
+//! Keymap of the keypad, read from a TOML config file like:
+//!
+//! ```toml
+//! # Match the keys by "keycode", the symbol they type (the default),
+//! # or by "scancode", their position on a QWERTY keyboard
+//! mode = "scancode"
+//! # How far the left stick is pushed to press the d-pad, from 0 to 1
+//! stick_threshold = 0.3
+//!
+//! [keys]
+//! 5 = ["W", "Space"]
+//! A = "Left Shift"
+//!
+//! [buttons]
+//! 5 = ["a", "rightshoulder"]
+//!
+//! # Overrides for the ROM with this hash, printed by --rom-hash
+//! [rom.0123456789abcdef]
+//! keys = { 2 = "Up", 8 = "Down", 4 = "Left", 6 = "Right" }
+//! buttons = { 4 = "a", 6 = "b" }
+//! ```
+//!
+//! The keys are named as in SDL, and the buttons as in the SDL game
+//! controller mappings. The keypad keys that aren't given keep the
+//! 1234/QWER/ASDF/ZXCV layout, and the buttons of the d-pad on 2, 4,
+//! 6 and 8 and the face buttons A, B, X and Y on 5, A, B and F. A key or
+//! button bound to a keypad key no longer presses the one it did before.
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sdl2::controller::Button;
+use sdl2::keyboard::{Keycode, Scancode};
+use serde::Deserialize;
+
+use cheap8_frontend::DEFAULT_LAYOUT;
+
+// Default buttons, with the keypad key they press.
+const DEFAULT_BUTTONS: [(usize, &str); 8] = [
+    (0x2, "dpup"),
+    (0x4, "dpleft"),
+    (0x6, "dpright"),
+    (0x8, "dpdown"),
+    (0x5, "a"),
+    (0xA, "b"),
+    (0xB, "x"),
+    (0xF, "y"),
+];
+const DEFAULT_STICK_THRESHOLD: f32 = 0.5;
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    mode: Option<Mode>,
+    stick_threshold: Option<f32>,
+    #[serde(default)]
+    keys: BTreeMap<String, Names>,
+    #[serde(default)]
+    buttons: BTreeMap<String, Names>,
+    #[serde(default)]
+    rom: BTreeMap<String, RomConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RomConfig {
+    mode: Option<Mode>,
+    #[serde(default)]
+    keys: BTreeMap<String, Names>,
+    #[serde(default)]
+    buttons: BTreeMap<String, Names>,
+}
+
+/// How the keys of the config file are matched.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// By the symbol they type, following the layout of the keyboard.
+    Keycode,
+    /// By their position, whatever the layout of the keyboard.
+    Scancode,
+}
+
+// Key or button names bound to a keypad key.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Names {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Keycode(Keycode),
+    Scancode(Scancode),
+}
+
+/// Keys of the keyboard and buttons of the game
+/// controllers bound to the keys of the keypad.
+pub struct Keymap {
+    // Keyboard key, and the keypad key it presses.
+    bindings: Vec<(Key, usize)>,
+    buttons: Vec<(Button, usize)>,
+    stick_threshold: i16,
+}
+
+/// Error raised when the config file can't be used.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file couldn't be read.
+    Io(PathBuf, io::Error),
+    /// The file isn't a valid config.
+    Toml(PathBuf, toml::de::Error),
+    /// `name` isn't a key of the keypad, 0 to F.
+    UnknownKeypadKey { name: String },
+    /// `name` isn't the name of an SDL key.
+    UnknownKey { name: String },
+    /// `name` isn't the name of a game controller button.
+    UnknownButton { name: String },
+    /// The stick threshold isn't between 0 and 1.
+    InvalidStickThreshold { threshold: f32 },
+    /// `hash` isn't a ROM hash, 16 hexadecimal digits.
+    InvalidRomHash { hash: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "couldn't read {}: {}", path.display(), e),
+            ConfigError::Toml(path, e) => write!(f, "invalid config {}: {}", path.display(), e),
+            ConfigError::UnknownKeypadKey { name } => {
+                write!(f, "unknown keypad key `{}`, expected 0 to F", name)
+            }
+            ConfigError::UnknownKey { name } => write!(f, "unknown key `{}`", name),
+            ConfigError::UnknownButton { name } => write!(f, "unknown button `{}`", name),
+            ConfigError::InvalidStickThreshold { threshold } => write!(
+                f,
+                "invalid stick threshold {}, expected a number between 0 and 1",
+                threshold
+            ),
+            ConfigError::InvalidRomHash { hash } => write!(
+                f,
+                "invalid ROM hash `{}`, expected 16 hexadecimal digits",
+                hash
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io(_, e) => Some(e),
+            ConfigError::Toml(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Keymap {
+    /// Read the keymap of the ROM hashed `rom_hash` from the config file
+    /// at `path`, or else at `~/.config/cheap8/config.toml` if it exists.
+    pub fn load(path: Option<&Path>, rom_hash: u64) -> Result<Self, ConfigError> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Keymap::default()),
+            },
+        };
+        let src = match fs::read_to_string(&path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => {
+                return Ok(Keymap::default())
+            }
+            Err(e) => return Err(ConfigError::Io(path, e)),
+        };
+        let config = toml::from_str(&src).map_err(|e| ConfigError::Toml(path, e))?;
+        Self::from_config(config, rom_hash)
+    }
+
+    fn from_config(config: Config, rom_hash: u64) -> Result<Self, ConfigError> {
+        let mut names = DEFAULT_LAYOUT.map(|key| vec![key.to_string()]);
+        let mut button_names: [Vec<String>; 16] = Default::default();
+        for (hex_key, name) in DEFAULT_BUTTONS {
+            button_names[hex_key].push(name.to_string());
+        }
+        let mut mode = config.mode.unwrap_or(Mode::Keycode);
+        bind(&mut names, config.keys)?;
+        bind(&mut button_names, config.buttons)?;
+        for (hash, rom) in config.rom {
+            let valid = hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit());
+            if !valid {
+                return Err(ConfigError::InvalidRomHash { hash });
+            }
+            if u64::from_str_radix(&hash, 16) == Ok(rom_hash) {
+                mode = rom.mode.unwrap_or(mode);
+                bind(&mut names, rom.keys)?;
+                bind(&mut button_names, rom.buttons)?;
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for (hex_key, names) in names.iter().enumerate() {
+            for name in names {
+                let key = match mode {
+                    Mode::Keycode => Keycode::from_name(name).map(Key::Keycode),
+                    Mode::Scancode => Scancode::from_name(name).map(Key::Scancode),
+                };
+                let key = key.ok_or_else(|| ConfigError::UnknownKey { name: name.clone() })?;
+                bindings.push((key, hex_key));
+            }
+        }
+        let mut buttons = Vec::new();
+        for (hex_key, names) in button_names.iter().enumerate() {
+            for name in names {
+                let button = Button::from_string(name)
+                    .ok_or_else(|| ConfigError::UnknownButton { name: name.clone() })?;
+                buttons.push((button, hex_key));
+            }
+        }
+
+        let threshold = config.stick_threshold.unwrap_or(DEFAULT_STICK_THRESHOLD);
+        if !(threshold > 0.0 && threshold <= 1.0) {
+            return Err(ConfigError::InvalidStickThreshold { threshold });
+        }
+        Ok(Keymap {
+            bindings,
+            buttons,
+            stick_threshold: (threshold * i16::MAX as f32) as i16,
+        })
+    }
+
+    /// Keypad keys pressed by the key at `scancode`.
+    pub fn keys(&self, scancode: Scancode) -> impl Iterator<Item = usize> + '_ {
+        let keycode = Keycode::from_scancode(scancode);
+        self.bindings
+            .iter()
+            .filter(move |(key, _)| match key {
+                Key::Keycode(key) => Some(*key) == keycode,
+                Key::Scancode(key) => *key == scancode,
+            })
+            .map(|(_, hex_key)| *hex_key)
+    }
+
+    /// Game controller buttons, with the keypad key they press.
+    pub fn buttons(&self) -> &[(Button, usize)] {
+        &self.buttons
+    }
+
+    /// Value past which the axes of the left stick press the d-pad.
+    pub fn stick_threshold(&self) -> i16 {
+        self.stick_threshold
+    }
+}
+
+impl Default for Keymap {
+    /// The 1234/QWER/ASDF/ZXCV layout, by keycode.
+    fn default() -> Self {
+        Self::from_config(Config::default(), 0).expect("the default layout is valid")
+    }
+}
+
+// Replace the names of the keypad keys given in `keys`. The keys
+// they name no longer press the other keypad keys.
+fn bind(names: &mut [Vec<String>; 16], keys: BTreeMap<String, Names>) -> Result<(), ConfigError> {
+    let mut bound = [false; 16];
+    for (name, keys) in keys {
+        let hex_key = match usize::from_str_radix(&name, 16) {
+            Ok(hex_key) if name.len() == 1 => hex_key,
+            _ => return Err(ConfigError::UnknownKeypadKey { name }),
+        };
+        names[hex_key] = match keys {
+            Names::One(key) => vec![key],
+            Names::Many(keys) => keys,
+        };
+        bound[hex_key] = true;
+    }
+
+    let taken: Vec<String> = (0..16)
+        .filter(|hex_key| bound[*hex_key])
+        .flat_map(|hex_key| names[hex_key].clone())
+        .collect();
+    for (hex_key, names) in names.iter_mut().enumerate() {
+        if !bound[hex_key] {
+            names.retain(|name| !taken.iter().any(|key| key.eq_ignore_ascii_case(name)));
+        }
+    }
+    Ok(())
+}
+
+// `cheap8/config.toml` in the config directory of the user.
+fn default_path() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("cheap8").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str, rom_hash: u64) -> Result<Keymap, ConfigError> {
+        Keymap::from_config(toml::from_str(src).unwrap(), rom_hash)
+    }
+
+    // Keys bound to the keypad key `hex_key`.
+    fn bound(keymap: &Keymap, hex_key: usize) -> Vec<Key> {
+        keymap
+            .bindings
+            .iter()
+            .filter(|(_, bound)| *bound == hex_key)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    #[test]
+    fn default_layout() {
+        let keymap = Keymap::default();
+        assert_eq!(bound(&keymap, 0x1), [Key::Keycode(Keycode::Num1)]);
+        assert_eq!(bound(&keymap, 0x5), [Key::Keycode(Keycode::W)]);
+        assert_eq!(bound(&keymap, 0x0), [Key::Keycode(Keycode::X)]);
+        assert_eq!(bound(&keymap, 0xF), [Key::Keycode(Keycode::V)]);
+        assert_eq!(keymap.bindings.len(), 16);
+        assert!(keymap.buttons().contains(&(Button::DPadUp, 0x2)));
+        assert!(keymap.buttons().contains(&(Button::A, 0x5)));
+        assert_eq!(keymap.stick_threshold(), i16::MAX / 2);
+    }
+
+    #[test]
+    fn rebinding_takes_the_key_from_its_default() {
+        let keymap = parse("[keys]\n5 = [\"x\", \"Space\"]\nA = \"W\"", 0).unwrap();
+        assert_eq!(
+            bound(&keymap, 0x5),
+            [Key::Keycode(Keycode::X), Key::Keycode(Keycode::Space)]
+        );
+        assert_eq!(bound(&keymap, 0xA), [Key::Keycode(Keycode::W)]);
+        // X pressed 0 and is now only bound to 5, whatever its case
+        assert_eq!(bound(&keymap, 0x0), []);
+        assert_eq!(bound(&keymap, 0x6), [Key::Keycode(Keycode::E)]);
+    }
+
+    #[test]
+    fn rom_overrides_the_global_keymap() {
+        let src = "
+            [keys]
+            5 = \"Space\"
+            [rom.00000000000000ab]
+            mode = \"scancode\"
+            keys = { 6 = \"Space\" }
+        ";
+        let keymap = parse(src, 0xAB).unwrap();
+        assert_eq!(bound(&keymap, 0x6), [Key::Scancode(Scancode::Space)]);
+        // Space is taken by 6, 5 is left without key
+        assert_eq!(bound(&keymap, 0x5), []);
+        assert_eq!(bound(&keymap, 0x4), [Key::Scancode(Scancode::Q)]);
+
+        let keymap = parse(src, 0xCD).unwrap();
+        assert_eq!(bound(&keymap, 0x5), [Key::Keycode(Keycode::Space)]);
+        assert_eq!(bound(&keymap, 0x6), [Key::Keycode(Keycode::E)]);
+    }
+
+    #[test]
+    fn scancode_mode() {
+        let keymap = parse("mode = \"scancode\"\n[keys]\n5 = \"Up\"", 0).unwrap();
+        assert_eq!(bound(&keymap, 0x5), [Key::Scancode(Scancode::Up)]);
+        assert_eq!(bound(&keymap, 0x4), [Key::Scancode(Scancode::Q)]);
+        // Whatever the layout, the key in the place of Q presses 4
+        assert_eq!(keymap.keys(Scancode::Q).collect::<Vec<_>>(), [0x4]);
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        let error = parse("[keys]\n5 = \"Nope\"", 0).err().unwrap();
+        assert!(matches!(&error, ConfigError::UnknownKey { name } if name == "Nope"));
+        assert_eq!(error.to_string(), "unknown key `Nope`");
+
+        let error = parse("[keys]\nG = \"W\"", 0).err().unwrap();
+        assert_eq!(error.to_string(), "unknown keypad key `G`, expected 0 to F");
+        let error = parse("[rom.ab]\nkeys = {}", 0).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid ROM hash `ab`, expected 16 hexadecimal digits"
+        );
+    }
+}
@@ -1,3 +1,4 @@
 pub mod drivers;
+pub mod keymap;
 mod parse_args;
 
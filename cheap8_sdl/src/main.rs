@@ -1,49 +1,63 @@
 use clap::Parser;
-use sdl2;
+use sdl2::messagebox::{self, MessageBoxFlag};
 
-use std::thread;
-use std::time::Duration;
+use std::process::ExitCode;
 
 mod drivers;
 use drivers::AudioDriver;
 use drivers::DisplayDriver;
 use drivers::InputDriver;
 
-use cheap8_core::{Cpu, Output};
+mod keymap;
+use keymap::Keymap;
+
+use cheap8_frontend::cli;
+use cheap8_frontend::repl::Session;
 
 mod parse_args;
 use parse_args::Cli;
 
-pub fn main() {
+pub fn main() -> ExitCode {
     let args = Cli::parse();
-    let sdl_context = sdl2::init().unwrap();
-    let mut display_driver = DisplayDriver::new(&sdl_context, &args)
-        .expect("Failed to create a display driver");
-    let mut input_driver = InputDriver::new(&sdl_context)
-        .expect("Failed to create an input driver");
-    let audio_driver = AudioDriver::new(&sdl_context)
-        .expect("Failed to create an audio driver");
-
-    let mut cpu = Cpu::new();
-    cpu.reset();
-    cpu.load(args.path.to_str().unwrap());
-
-    while let Some(inputs) = input_driver.poll() {
-        let Output {
-            screen,
-            screen_update,
-            beep,
-        } = cpu.cycle(&inputs);
-        if screen_update {
-            display_driver.draw(screen);
+    let mut runner = match cli::setup(&args.common) {
+        Ok(Some(runner)) => runner,
+        Ok(None) => return ExitCode::SUCCESS,
+        Err(message) => {
+            report(&message);
+            return ExitCode::FAILURE;
         }
+    };
 
-        if beep {
-            audio_driver.play();
-        } else {
-            audio_driver.stop();
+    let sdl_context = sdl2::init().unwrap();
+    let mut display_driver =
+        DisplayDriver::new(&sdl_context, &args).expect("Failed to create a display driver");
+    let mut input_driver =
+        InputDriver::new(&sdl_context).expect("Failed to create an input driver");
+    let mut audio_driver =
+        AudioDriver::new(&sdl_context).expect("Failed to create an audio driver");
+    match Keymap::load(args.config.as_deref(), runner.cpu().rom_hash()) {
+        Ok(keymap) => input_driver.set_keymap(keymap),
+        Err(e) => {
+            report(&format!("Failed to read the config: {}", e));
+            return ExitCode::FAILURE;
         }
+    }
+    if args.common.debug {
+        runner.set_monitor(Box::new(Session::new(runner.ipf())));
+    }
 
-        thread::sleep(Duration::from_millis(1));
+    let result = runner.run(&mut display_driver, &mut input_driver, &mut audio_driver);
+    match cli::finish(&args.common, runner, result, "SDL failed") {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(message) => {
+            report(&message);
+            ExitCode::FAILURE
+        }
     }
 }
+
+// Print the error on stderr and show it in a message box.
+fn report(message: &str) {
+    eprintln!("{}", message);
+    let _ = messagebox::show_simple_message_box(MessageBoxFlag::ERROR, "Cheap8", message, None);
+}
@@ -1,37 +1,35 @@
-use std::num::ParseIntError;
+use cheap8_frontend::cli::Args;
 
-fn parse_color(src: &str) -> Result<u32, ParseIntError> {
-    if src.starts_with("0x") {
-        return u32::from_str_radix(&src[2..], 16);
-    } else {
-        return u32::from_str_radix(src, 10);
-    }
-}
+use crate::drivers::Scaling;
 
 #[derive(clap::Parser)]
 pub struct Cli {
-    /// Path to the rom file
-    #[structopt(parse(from_os_str))]
-    pub path: std::path::PathBuf,
-
-    /// Pixels color, in RGB format
-    #[structopt(short, long, default_value = "0x00F0F0", parse(try_from_str = parse_color))]
-    pub pixel_color: u32,
-    /// Background color, in RGB format
-    #[structopt(short, long, default_value = "0x000000", parse(try_from_str = parse_color))]
-    pub bg_color: u32,
+    #[structopt(flatten)]
+    pub common: Args,
 
-    /// Scale of the scree, 1 corresponds to a 64x32 window
+    /// Scale of the screen, 1 corresponds to a 64x32 window
     #[structopt(short, long, default_value = "14")]
     pub scale_factor: u32,
-}
+    /// How the screen is scaled to the window, by whole pixels (integer)
+    /// or as large as it fits (fit), with black bars around it
+    #[structopt(long, default_value = "fit", parse(try_from_str = parse_scaling))]
+    pub scaling: Scaling,
+    /// Start in fullscreen, toggled with F11
+    #[structopt(short, long)]
+    pub fullscreen: bool,
 
-impl Cli {
-    pub fn rgb_color(color: u32) -> (u8, u8, u8) {
-        let r = ((color & 0xFF0000) >> 16) as u8;
-        let g = ((color & 0x00FF00) >> 8) as u8;
-        let b = (color & 0x0000FF) as u8;
+    /// Config file with the keymap, instead of ~/.config/cheap8/config.toml
+    #[structopt(long, parse(from_os_str))]
+    pub config: Option<std::path::PathBuf>,
+}
 
-        (r, g, b)
+fn parse_scaling(src: &str) -> Result<Scaling, String> {
+    match src {
+        "integer" => Ok(Scaling::Integer),
+        "fit" => Ok(Scaling::Fit),
+        _ => Err(format!(
+            "unknown scaling `{}`, expected integer or fit",
+            src
+        )),
     }
 }
@@ -0,0 +1,39 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use cheap8_frontend::AudioSink;
+
+/// Struct that drives audio with the terminal bell, which
+/// rings once when a sound starts rather than playing it.
+pub struct AudioDriver {
+    playing: bool,
+}
+
+impl AudioDriver {
+    pub fn new() -> Self {
+        AudioDriver { playing: false }
+    }
+}
+
+impl AudioSink for AudioDriver {
+    /// Ring the bell, unless it is already playing.
+    fn play(&mut self, _: Option<&[u8; 16]>, _: u8) -> Result<(), Box<dyn Error>> {
+        if !self.playing {
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+            self.playing = true;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.playing = false;
+    }
+}
+
+impl Default for AudioDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,139 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+use crossterm::cursor::MoveTo;
+use crossterm::style::{Print, ResetColor};
+use crossterm::terminal::{self, Clear, ClearType};
+use crossterm::{execute, queue};
+
+// Printed lines kept, more than fit under the screen.
+const MAX_LINES: usize = 100;
+
+/// Lines printed by the debugger and the command being typed, shown
+/// under the screen. Tab moves the keyboard between the keypad and
+/// the command line.
+pub struct Console {
+    lines: VecDeque<String>,
+    // Text printed after the last full line.
+    partial: String,
+    command: String,
+    // True iff the keys type a command instead of pressing the keypad.
+    focused: bool,
+    // Row of the terminal it starts at, under the screen.
+    top: u16,
+    // Where the commands are sent once entered.
+    sender: Sender<String>,
+}
+
+impl Console {
+    pub fn new(sender: Sender<String>) -> Self {
+        Console {
+            lines: VecDeque::new(),
+            partial: String::new(),
+            command: String::new(),
+            focused: false,
+            top: 0,
+            sender,
+        }
+    }
+
+    /// True iff the keys type a command instead of pressing the keypad.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Move the keyboard between the keypad and the command line.
+    pub fn toggle_focus(&mut self) -> io::Result<()> {
+        self.focused = !self.focused;
+        self.draw()
+    }
+
+    /// Type `c` at the end of the command.
+    pub fn type_char(&mut self, c: char) -> io::Result<()> {
+        self.command.push(c);
+        self.draw()
+    }
+
+    /// Erase the last character of the command.
+    pub fn erase(&mut self) -> io::Result<()> {
+        self.command.pop();
+        self.draw()
+    }
+
+    /// Send the command typed, echoed with the printed lines.
+    pub fn enter(&mut self) -> io::Result<()> {
+        let command = std::mem::take(&mut self.command);
+        self.print(&format!("> {}\n", command));
+        // The debugger is gone once the emulator quits
+        let _ = self.sender.send(command);
+        self.draw()
+    }
+
+    /// Start at row `top` of the terminal, redrawn if it moved.
+    pub fn place(&mut self, top: u16) -> io::Result<()> {
+        if top != self.top {
+            self.top = top;
+            self.draw()?;
+        }
+        Ok(())
+    }
+
+    fn print(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' => self.lines.push_back(std::mem::take(&mut self.partial)),
+                c => self.partial.push(c),
+            }
+        }
+        while self.lines.len() > MAX_LINES {
+            self.lines.pop_front();
+        }
+    }
+
+    // Draw the last lines that fit between the top and the command line.
+    fn draw(&self) -> io::Result<()> {
+        let (_, rows) = terminal::size()?;
+        let mut stdout = io::stdout();
+        let prompt_row = rows.saturating_sub(1).max(self.top);
+        let shown = (prompt_row - self.top) as usize;
+        queue!(stdout, ResetColor)?;
+        let skipped = self.lines.len().saturating_sub(shown);
+        for (i, line) in self.lines.iter().skip(skipped).enumerate() {
+            let row = self.top + i as u16;
+            queue!(
+                stdout,
+                MoveTo(0, row),
+                Clear(ClearType::CurrentLine),
+                Print(line)
+            )?;
+        }
+        let prompt = if self.focused {
+            format!("> {}_", self.command)
+        } else {
+            "(Tab to type a debugger command)".to_string()
+        };
+        execute!(
+            stdout,
+            MoveTo(0, prompt_row),
+            Clear(ClearType::CurrentLine),
+            Print(prompt)
+        )
+    }
+}
+
+/// Prints to a [`Console`], for the debugger.
+pub struct ConsoleWriter(pub Rc<RefCell<Console>>);
+
+impl Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().print(&String::from_utf8_lossy(buf));
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow().draw()
+    }
+}
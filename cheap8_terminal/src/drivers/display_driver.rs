@@ -0,0 +1,184 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::io::{self, Stdout, Write};
+use std::rc::Rc;
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+
+use cheap8_frontend::VideoSink;
+
+use super::Console;
+use crate::parse_args::Cli;
+
+// Braille dot of each pixel of a 2x4 cell, by row then column.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Struct that can display chip8 screen to the terminal, in
+/// the alternate screen which is left when it is dropped.
+pub struct DisplayDriver {
+    // Color of each pixel value: background, first plane,
+    // second plane and both planes.
+    palette: [Color; 4],
+    braille: bool,
+    stdout: Stdout,
+    // Width of the last drawn image, to clear the
+    // terminal when the resolution changes.
+    width: usize,
+    // Debugger console shown under the screen, if debugging.
+    console: Option<Rc<RefCell<Console>>>,
+}
+
+impl DisplayDriver {
+    /// Create new driver. `args` are used to know
+    /// the characters and colors to draw with.
+    pub fn new(args: &Cli) -> io::Result<Self> {
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        let palette = args
+            .common
+            .palette()
+            .map(|(r, g, b)| Color::Rgb { r, g, b });
+
+        Ok(DisplayDriver {
+            palette,
+            braille: args.braille,
+            stdout,
+            width: 0,
+            console: None,
+        })
+    }
+
+    /// Show `console` under the screen.
+    pub fn set_console(&mut self, console: Rc<RefCell<Console>>) {
+        self.console = Some(console);
+    }
+
+    // Two pixels per character, the upper half block being
+    // drawn with the top one and its background with the other.
+    fn draw_half_blocks(&mut self, image: &[u8], width: usize, height: usize) -> io::Result<()> {
+        for row in 0..height / 2 {
+            queue!(self.stdout, MoveTo(0, row as u16))?;
+            let mut colors = None;
+            for x in 0..width {
+                let (top, bottom) = half_block(image, width, x, row);
+                let (top, bottom) = (self.palette[top as usize], self.palette[bottom as usize]);
+                if colors != Some((top, bottom)) {
+                    queue!(
+                        self.stdout,
+                        SetForegroundColor(top),
+                        SetBackgroundColor(bottom)
+                    )?;
+                    colors = Some((top, bottom));
+                }
+                queue!(self.stdout, Print('▀'))?;
+            }
+        }
+        Ok(())
+    }
+
+    // 2x4 pixels per character, a pixel being on iff it isn't the
+    // background, whatever its planes.
+    fn draw_braille(&mut self, image: &[u8], width: usize, height: usize) -> io::Result<()> {
+        queue!(
+            self.stdout,
+            SetForegroundColor(self.palette[1]),
+            SetBackgroundColor(self.palette[0])
+        )?;
+        for row in 0..height / 4 {
+            let line: String = (0..width / 2)
+                .map(|column| braille(image, width, column, row))
+                .collect();
+            queue!(self.stdout, MoveTo(0, row as u16), Print(line))?;
+        }
+        Ok(())
+    }
+}
+
+// Pixels of `image` in the top and bottom halves of the
+// character at column `x` of `row`.
+fn half_block(image: &[u8], width: usize, x: usize, row: usize) -> (u8, u8) {
+    (image[x + 2 * row * width], image[x + (2 * row + 1) * width])
+}
+
+// Braille character with the dots of the pixels of `image`
+// that are on, in the 2x4 cell at `column` and `row`.
+fn braille(image: &[u8], width: usize, column: usize, row: usize) -> char {
+    let mut dots = 0;
+    for (dy, row_dots) in BRAILLE_DOTS.iter().enumerate() {
+        for (dx, dot) in row_dots.iter().enumerate() {
+            if image[2 * column + dx + (4 * row + dy) * width] != 0 {
+                dots |= dot;
+            }
+        }
+    }
+    char::from_u32(0x2800 + dots).unwrap_or(' ')
+}
+
+impl VideoSink for DisplayDriver {
+    /// Draw `image` of size `width` x `height` in the top left
+    /// corner of the terminal, with the console under it.
+    fn draw(&mut self, image: &[u8], width: usize, height: usize) -> Result<(), Box<dyn Error>> {
+        if width != self.width {
+            queue!(self.stdout, ResetColor, Clear(ClearType::All))?;
+            self.width = width;
+        }
+        let rows = if self.braille {
+            self.draw_braille(image, width, height)?;
+            height / 4
+        } else {
+            self.draw_half_blocks(image, width, height)?;
+            height / 2
+        };
+        queue!(self.stdout, ResetColor)?;
+        self.stdout.flush()?;
+        if let Some(console) = &self.console {
+            // Moved when the resolution changes, clearing the terminal
+            console.borrow_mut().place(rows as u16 + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DisplayDriver {
+    fn drop(&mut self) {
+        let _ = execute!(self.stdout, ResetColor, Show, LeaveAlternateScreen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Image from rows of digits, the values of the pixels.
+    fn pixels(rows: &[&str]) -> Vec<u8> {
+        rows.iter()
+            .flat_map(|row| row.bytes().map(|digit| digit - b'0'))
+            .collect()
+    }
+
+    #[test]
+    fn half_blocks() {
+        let image = pixels(&["0123", "3210", "1111", "0000"]);
+        assert_eq!(half_block(&image, 4, 0, 0), (0, 3));
+        assert_eq!(half_block(&image, 4, 1, 0), (1, 2));
+        assert_eq!(half_block(&image, 4, 3, 0), (3, 0));
+        assert_eq!(half_block(&image, 4, 2, 1), (1, 0));
+    }
+
+    #[test]
+    fn braille_dots() {
+        let image = pixels(&["1000", "0100", "0010", "0013"]);
+        // Dots 1 and 5 on the left
+        assert_eq!(braille(&image, 4, 0, 0), '\u{2811}');
+        // Dots 3, 7 and 8 on the right, whatever the planes
+        assert_eq!(braille(&image, 4, 1, 0), '\u{28C4}');
+
+        let blank = pixels(&["00", "00", "00", "00"]);
+        assert_eq!(braille(&blank, 2, 0, 0), '\u{2800}');
+        let full = pixels(&["12", "31", "11", "11"]);
+        assert_eq!(braille(&full, 2, 0, 0), '\u{28FF}');
+    }
+}
@@ -0,0 +1,175 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::io;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::execute;
+use crossterm::terminal;
+
+use cheap8_frontend::{Input, InputSource, DEFAULT_LAYOUT};
+
+use super::Console;
+
+// Most terminals only report key presses, repeated while the key is
+// held, so a key counts as pressed for a while after each of them.
+// It has to outlast the delay before the first repetition.
+const HOLD: Duration = Duration::from_millis(550);
+
+/// Struct that deals with inputs events, with the terminal in raw
+/// mode until it is dropped.
+pub struct InputDriver {
+    // True iff the terminal reports key releases.
+    releases: bool,
+    // When each key was last pressed or repeated, `None` if released.
+    pressed_at: [Option<Instant>; 16],
+    // Same for the rewind key (backspace).
+    rewind_at: Option<Instant>,
+    // Debugger console the commands are typed in, if debugging.
+    console: Option<Rc<RefCell<Console>>>,
+    // True until the first frame, to draw the screen even if the
+    // debugger starts paused.
+    redraw: bool,
+}
+
+impl InputDriver {
+    pub fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+        Ok(InputDriver {
+            releases,
+            pressed_at: [None; 16],
+            rewind_at: None,
+            console: None,
+            redraw: true,
+        })
+    }
+
+    /// Type the commands of the debugger in `console`
+    /// after pressing Tab, until it is pressed again.
+    pub fn set_console(&mut self, console: Rc<RefCell<Console>>) {
+        self.console = Some(console);
+    }
+
+    // Keypad key pressed by typing `c`, the same as with SDL.
+    fn key_char_to_hex(c: char) -> Option<usize> {
+        DEFAULT_LAYOUT
+            .iter()
+            .position(|key| key.eq_ignore_ascii_case(&c))
+    }
+}
+
+impl InputSource for InputDriver {
+    /// Return `Some(key_pressed)` if it reads keypad inputs,
+    /// where `true` mean pressed, and whether the rewind key
+    /// (backspace) is held. Keys typed in the console don't count.
+    /// Return None if the user pressed Escape or Ctrl-C.
+    fn poll(&mut self) -> Result<Option<Input>, Box<dyn Error>> {
+        while event::poll(Duration::ZERO)? {
+            let KeyEvent {
+                code,
+                modifiers,
+                kind,
+                ..
+            } = match event::read()? {
+                Event::Key(key) => key,
+                _ => continue,
+            };
+            match code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+                _ => (),
+            }
+            let pressed_at = match kind {
+                KeyEventKind::Release => None,
+                _ => Some(Instant::now()),
+            };
+
+            if let Some(console) = &self.console {
+                let mut console = console.borrow_mut();
+                if code == KeyCode::Tab && pressed_at.is_some() {
+                    console.toggle_focus()?;
+                    self.pressed_at = [None; 16];
+                    self.rewind_at = None;
+                    continue;
+                }
+                if console.is_focused() {
+                    match code {
+                        _ if pressed_at.is_none() => (),
+                        KeyCode::Char(c) => console.type_char(c)?,
+                        KeyCode::Backspace => console.erase()?,
+                        KeyCode::Enter => console.enter()?,
+                        _ => (),
+                    }
+                    continue;
+                }
+            }
+
+            match code {
+                KeyCode::Char(c) => {
+                    if let Some(hex_key) = Self::key_char_to_hex(c) {
+                        self.pressed_at[hex_key] = pressed_at;
+                    }
+                }
+                KeyCode::Backspace => self.rewind_at = pressed_at,
+                _ => (),
+            }
+        }
+
+        let now = Instant::now();
+        let releases = self.releases;
+        let held =
+            |pressed_at: Option<Instant>| pressed_at.is_some_and(|at| releases || now - at < HOLD);
+        Ok(Some(Input {
+            keys: self.pressed_at.map(held),
+            rewind: held(self.rewind_at),
+            command: None,
+            redraw: std::mem::take(&mut self.redraw),
+        }))
+    }
+}
+
+impl Drop for InputDriver {
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypad_layout() {
+        let keys: Vec<_> = "1234qwerasdfzxcv"
+            .chars()
+            .map(InputDriver::key_char_to_hex)
+            .collect();
+        let expected = [
+            0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+        ];
+        assert_eq!(keys, expected.map(Some));
+    }
+
+    #[test]
+    fn case_and_other_keys() {
+        assert_eq!(InputDriver::key_char_to_hex('W'), Some(0x5));
+        assert_eq!(InputDriver::key_char_to_hex('V'), Some(0xF));
+        assert_eq!(InputDriver::key_char_to_hex('5'), None);
+        assert_eq!(InputDriver::key_char_to_hex('t'), None);
+        assert_eq!(InputDriver::key_char_to_hex(' '), None);
+    }
+}
@@ -0,0 +1,9 @@
+mod audio_driver;
+mod console;
+mod display_driver;
+mod input_driver;
+
+pub use audio_driver::AudioDriver;
+pub use console::{Console, ConsoleWriter};
+pub use display_driver::DisplayDriver;
+pub use input_driver::InputDriver;
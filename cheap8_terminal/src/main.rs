@@ -0,0 +1,56 @@
+use clap::Parser;
+
+use std::cell::RefCell;
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::mpsc;
+
+mod drivers;
+use drivers::AudioDriver;
+use drivers::DisplayDriver;
+use drivers::InputDriver;
+use drivers::{Console, ConsoleWriter};
+
+use cheap8_frontend::cli;
+use cheap8_frontend::repl::Session;
+
+mod parse_args;
+use parse_args::Cli;
+
+pub fn main() -> ExitCode {
+    let args = Cli::parse();
+    // The terminal is restored when `run` returns,
+    // the errors can only be printed afterwards
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Cli) -> Result<(), String> {
+    let mut runner = match cli::setup(&args.common)? {
+        Some(runner) => runner,
+        None => return Ok(()),
+    };
+
+    let mut display_driver =
+        DisplayDriver::new(args).map_err(|e| format!("Failed to set up the terminal: {}", e))?;
+    let mut input_driver =
+        InputDriver::new().map_err(|e| format!("Failed to set up the terminal: {}", e))?;
+    let mut audio_driver = AudioDriver::new();
+    if args.common.debug {
+        let (sender, lines) = mpsc::channel();
+        let console = Rc::new(RefCell::new(Console::new(sender)));
+        display_driver.set_console(console.clone());
+        input_driver.set_console(console.clone());
+        let output = Box::new(ConsoleWriter(console));
+        runner.set_monitor(Box::new(Session::with_io(runner.ipf(), lines, output)));
+    }
+
+    let result = runner.run(&mut display_driver, &mut input_driver, &mut audio_driver);
+    cli::finish(&args.common, runner, result, "Failed to use the terminal")?;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use cheap8_frontend::cli::Args;
+
+#[derive(clap::Parser)]
+pub struct Cli {
+    #[structopt(flatten)]
+    pub common: Args,
+
+    /// Draw with braille characters, 2x4 pixels per character, instead of
+    /// half blocks. Smaller, but only the pixel color is used
+    #[structopt(long)]
+    pub braille: bool,
+}
@@ -0,0 +1,96 @@
+//! # cheap8_wasm
+//! The browser client of cheap8, exposing an [`Emulator`]
+//! to JavaScript with wasm-bindgen. `www/index.html`
+//! draws it on a canvas.
+use cheap8_core::{Cpu, Quirks};
+use wasm_bindgen::prelude::*;
+
+/// Instructions per frame, until changed with [`Emulator::set_ipf`].
+const DEFAULT_IPF: usize = 15;
+
+/// A CHIP-8 CPU driven from JavaScript, one frame at a time.
+#[wasm_bindgen]
+pub struct Emulator {
+    cpu: Cpu,
+    ipf: usize,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    /// Create an emulator with the modern quirks, CXNN drawing
+    /// from a generator seeded with `Math.random`.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Emulator {
+        let seed = js_sys::Math::random() * (1u64 << 53) as f64;
+        Emulator::with_seed(seed as u64)
+    }
+
+    /// Same as `new`, with the random generator seeded with
+    /// `seed` to reproduce a run.
+    pub fn with_seed(seed: u64) -> Emulator {
+        let mut cpu = Cpu::with_seed(Quirks::modern(), seed);
+        cpu.reset();
+        Emulator {
+            cpu,
+            ipf: DEFAULT_IPF,
+        }
+    }
+
+    /// Reset the CPU and load `rom`.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.cpu.reset();
+        self.cpu.load_bytes(rom)?;
+        Ok(())
+    }
+
+    /// Run a frame with `keys` pressed, bit `i` being set iff key `i`
+    /// is. Return true iff the screen should be redrawn.
+    pub fn run_frame(&mut self, keys: u16) -> Result<bool, JsError> {
+        let mut pressed = [false; 16];
+        for (key, pressed) in pressed.iter_mut().enumerate() {
+            *pressed = keys & 1 << key != 0;
+        }
+        Ok(self.cpu.run_frame(&pressed, self.ipf)?.screen_update)
+    }
+
+    /// The pixels row by row, each being the bitmask of
+    /// the planes it is set on.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        self.cpu.output().screen.to_vec()
+    }
+
+    /// Width of the screen in the current resolution.
+    pub fn width(&self) -> usize {
+        self.cpu.output().width
+    }
+
+    /// Height of the screen in the current resolution.
+    pub fn height(&self) -> usize {
+        self.cpu.output().height
+    }
+
+    /// True iff a sound should be played.
+    pub fn beeping(&self) -> bool {
+        self.cpu.output().beep
+    }
+
+    /// True iff the program exited (with 00FD).
+    pub fn exited(&self) -> bool {
+        self.cpu.has_exited()
+    }
+
+    pub fn ipf(&self) -> usize {
+        self.ipf
+    }
+
+    /// Execute `ipf` instructions per frame.
+    pub fn set_ipf(&mut self, ipf: usize) {
+        self.ipf = ipf;
+    }
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Emulator::new()
+    }
+}
@@ -0,0 +1,48 @@
+//! Run with `wasm-pack test --node cheap8_wasm`, or
+//! natively with `cargo test`.
+use cheap8_core::LORES_WIDTH;
+use cheap8_wasm::Emulator;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+fn emulator(rom: &[u8]) -> Emulator {
+    let mut emulator = Emulator::with_seed(0);
+    emulator.load_rom(rom).unwrap();
+    emulator
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn draws_in_the_framebuffer() {
+    // Draw the 5 of the font at (5, 0), then loop
+    let mut emulator = emulator(&[0x60, 0x05, 0xF0, 0x29, 0xD0, 0x15, 0x12, 0x06]);
+    assert!(emulator.run_frame(0).unwrap());
+    assert_eq!((emulator.width(), emulator.height()), (64, 32));
+    let screen = emulator.framebuffer();
+    assert_eq!(screen.len(), 64 * 32);
+    assert_eq!(&screen[4..10], &[0, 1, 1, 1, 1, 0]);
+    assert_eq!(
+        &screen[LORES_WIDTH + 4..LORES_WIDTH + 10],
+        &[0, 1, 0, 0, 0, 0]
+    );
+    assert!(!emulator.run_frame(0).unwrap());
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn beeps_with_the_sound_timer() {
+    // Set the sound timer to 2, then loop
+    let mut emulator = emulator(&[0x60, 0x02, 0xF0, 0x18, 0x12, 0x04]);
+    assert!(!emulator.beeping());
+    emulator.run_frame(0).unwrap();
+    assert!(emulator.beeping());
+    emulator.run_frame(0).unwrap();
+    assert!(!emulator.beeping());
+}
+
+#[wasm_bindgen_test(unsupported = test)]
+fn reads_the_keys_as_a_bitmask() {
+    // Exit once key 7 is pressed
+    let mut emulator = emulator(&[0x61, 0x07, 0xE1, 0x9E, 0x12, 0x02, 0x00, 0xFD]);
+    emulator.run_frame(1 << 6 | 1 << 8).unwrap();
+    assert!(!emulator.exited());
+    emulator.run_frame(1 << 7).unwrap();
+    assert!(emulator.exited());
+}
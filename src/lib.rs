@@ -2,6 +2,6 @@
 //!
 //! `Cheap8` is a simple Chip8 emulator, bundled with
 //! mutliple clients, like a sdl based, terminal based
-//! and hopefully a wasm one.
+//! and a wasm one.
 pub use cheap8_core;
 